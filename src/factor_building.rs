@@ -2,12 +2,18 @@ use std::iter::repeat_with;
 use std::ops::Rem;
 
 use crate::number_type::NumberOps;
+use crate::progress::{ProgressEvent, ProgressObserver};
 use crate::{sieve::SmoothNumber, solver::Solution};
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use num_bigint::BigUint;
-use num_traits::FromPrimitive;
-use rand::{thread_rng, Rng};
+use log::{debug, info};
+use num_bigint::{BigInt, BigUint};
+use num_traits::{FromPrimitive, Zero};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::ops::Range;
 
 pub fn is_big_square(number: &BigUint) -> bool {
     let t = number.sqrt();
@@ -18,7 +24,23 @@ pub fn euclid(x: &BigUint, y: &BigUint) -> BigUint {
     num_integer::gcd(x.clone(), y.clone())
 }
 
-/// `a` is expected to be greater
+/// returns `(gcd, n / gcd)` if `gcd(n, candidate)` is a nontrivial factor of `n`, i.e. neither `n`
+/// itself nor `1`.
+fn try_split(n: &BigUint, candidate: &BigUint) -> Option<(BigUint, BigUint)> {
+    let gcd = euclid(n, candidate);
+    if &gcd != n && gcd.bits() != 1 {
+        let other_term = n / &gcd;
+        return Some((gcd, other_term));
+    }
+    None
+}
+
+/// `a ≡ ±b (mod n)` is the classic degenerate outcome of a congruence-of-squares relation: both
+/// `gcd(n, a - b)` and `gcd(n, a + b)` come out as `1` or `n`, and the relation is usually just
+/// discarded. Before giving up, also try `gcd(n, a)` and `gcd(n, b)` directly: if either `a` or
+/// `b` itself shares a nontrivial factor with `n` - equivalently, has no modular inverse mod `n` -
+/// that gcd *is* a factor of `n`, independent of whether the combination they came from produced
+/// a genuine square relation at all.
 fn test_factorization(n: &BigUint, a: &BigUint, b: &BigUint) -> Option<(BigUint, BigUint)> {
     let mut a = a.rem(n);
     let mut b = b.rem(n);
@@ -27,26 +49,62 @@ fn test_factorization(n: &BigUint, a: &BigUint, b: &BigUint) -> Option<(BigUint,
         std::mem::swap(&mut a, &mut b);
     }
 
-    let gcd = euclid(n, &(a.clone() - b));
-    if &gcd != n && gcd.bits() != 1 {
-        let other_term = n / gcd.clone();
-        return Some((gcd, other_term));
-    }
-    None
+    try_split(n, &(&a - &b))
+        .or_else(|| try_split(n, &(&a + &b)))
+        .or_else(|| try_split(n, &a))
+        .or_else(|| try_split(n, &b))
 }
 
-fn is_zero(vec: &[bool]) -> bool {
-    vec.iter().all(|&item| !item)
-}
+/// extended Euclidean algorithm over `BigUint`, returning `value^-1 mod modulus` or `None` if
+/// `gcd(value, modulus) != 1`.
+pub(crate) fn mod_inverse_biguint(value: &BigUint, modulus: &BigUint) -> Option<BigUint> {
+    let (mut old_r, mut r) = (BigInt::from(value.clone()), BigInt::from(modulus.clone()));
+    let (mut old_s, mut s) = (BigInt::from(1), BigInt::from(0));
 
-#[inline]
-fn increase(vec: &mut [bool]) {
-    let mut carry = true;
-    for digit in vec {
-        let new_carry = *digit && carry;
-        *digit = *digit != carry;
-        carry = new_carry;
+    while !r.is_zero() {
+        let quotient = &old_r / &r;
+
+        let new_r = &old_r - &quotient * &r;
+        old_r = r;
+        r = new_r;
+
+        let new_s = &old_s - &quotient * &s;
+        old_s = s;
+        s = new_s;
+    }
+
+    if old_r != BigInt::from(1) {
+        return None;
     }
+
+    let modulus = BigInt::from(modulus.clone());
+    (((old_s % &modulus) + &modulus) % &modulus).to_biguint()
+}
+
+/// `solution.subsitute` is linear (over GF(2)) in the free variables, so the effect of flipping
+/// any single free variable can be precomputed once: for free variable at position `bit`,
+/// `flip_sets[bit]` holds every answer-vector index (the variable itself plus any dependents)
+/// whose inclusion flips when that one bit is toggled, with every other free variable held at
+/// zero. Flipping several free variables at once (as Gray code enumeration does, one at a time)
+/// is then just the symmetric difference of the relevant `flip_sets`, applied incrementally.
+fn free_variable_flip_sets(solution: &Solution) -> Vec<Vec<usize>> {
+    let limit = solution.free_variables.len();
+    let baseline = solution.subsitute(&vec![false; limit], false);
+
+    (0..limit)
+        .map(|bit| {
+            let mut probe = vec![false; limit];
+            probe[bit] = true;
+            let toggled = solution.subsitute(&probe, false);
+
+            baseline
+                .iter()
+                .zip(toggled.iter())
+                .enumerate()
+                .filter_map(|(idx, (&before, &after))| (before != after).then_some(idx))
+                .collect_vec()
+        })
+        .collect_vec()
 }
 
 lazy_static! {
@@ -115,18 +173,299 @@ fn search_lonelies<NT: NumberOps>(
     None
 }
 
+/// builds a `rayon` thread pool bounded by `max_workers`, so callers of the parallel search
+/// variants can cap resource use instead of always saturating every core. `None` leaves the
+/// worker count to rayon's own default (one per logical core).
+fn build_worker_pool(max_workers: Option<usize>) -> ThreadPool {
+    let mut builder = ThreadPoolBuilder::new();
+    if let Some(workers) = max_workers {
+        builder = builder.num_threads(workers);
+    }
+    builder
+        .build()
+        .expect("failed to build exhaustive/random search worker pool")
+}
+
+/// walks a contiguous range of Gray-code steps and reports the first nontrivial factor found,
+/// same as the body of `find_factor_exhaustive`'s loop. The running `(a, b)` state at the start
+/// of the range is rebuilt once from the inclusion set implied by `gray_code(range.start - 1)`
+/// (standard formula `k ^ (k >> 1)`), after which every step is the same O(1) update used by the
+/// single-threaded search.
+fn exhaustive_chunk<NT: NumberOps>(
+    n: &BigUint,
+    smoothies: &[SmoothNumber<NT>],
+    flip_sets: &[Vec<usize>],
+    range: Range<usize>,
+) -> Option<(BigUint, BigUint)> {
+    if range.start >= range.end {
+        return None;
+    }
+
+    let start_gray = (range.start - 1) ^ ((range.start - 1) >> 1);
+
+    let mut included = vec![false; smoothies.len()];
+    for (bit, flips) in flip_sets.iter().enumerate() {
+        if start_gray & (1 << bit) != 0 {
+            for &idx in flips {
+                included[idx] = !included[idx];
+            }
+        }
+    }
+
+    let mut a = ONE.clone();
+    let mut b = ONE.clone();
+    for (idx, &on) in included.iter().enumerate() {
+        if on {
+            let number = smoothies[idx].number.to_varsize();
+            a = (a * &number).rem(n);
+            b *= number.modpow(&TWO, n);
+        }
+    }
+
+    for step in range {
+        let bit = step.trailing_zeros() as usize;
+
+        for &idx in &flip_sets[bit] {
+            let number = smoothies[idx].number.to_varsize();
+            let residue = number.modpow(&TWO, n);
+
+            if included[idx] {
+                let Some(inverse) = mod_inverse_biguint(&number, n) else {
+                    let gcd = euclid(&number, n);
+                    return Some((gcd.clone(), n / gcd));
+                };
+
+                a = (a * inverse).rem(n);
+                b /= &residue;
+            } else {
+                a = (a * &number).rem(n);
+                b *= &residue;
+            }
+
+            included[idx] = !included[idx];
+        }
+
+        if let Some(sol) = test_factorization(n, &a, &b.sqrt()) {
+            return Some(sol);
+        }
+    }
+
+    None
+}
+
+/// multicore counterpart of `find_factor_exhaustive`: partitions the Gray-code index space
+/// `1..2^limit` into contiguous chunks (one per worker) and hands them to a bounded `rayon`
+/// thread pool. Once any chunk succeeds, `find_map_any` starts no further chunks; a chunk that is
+/// already running is not interrupted and walks its whole range before the call returns.
+pub fn find_factor_exhaustive_parallel<NT: NumberOps + Sync>(
+    n: &NT,
+    smoothies: &[SmoothNumber<NT>],
+    solution: &Solution,
+    max_workers: Option<usize>,
+    observer: &mut impl ProgressObserver,
+) -> Option<(BigUint, BigUint)> {
+    info!("using parallel exhaustive search");
+
+    if let Some(answ) = search_lonelies(n, smoothies, solution) {
+        observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: true });
+        observer.on_event(ProgressEvent::FactorFound);
+        return Some(answ);
+    }
+    observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: false });
+
+    let n_big = n.to_varsize();
+
+    if solution.free_variables.is_empty() {
+        return None;
+    }
+
+    let limit = solution.free_variables.len();
+    let total = 1usize << limit;
+
+    debug!("variables for parallel exhaustive search: {limit}");
+    observer.on_event(ProgressEvent::SearchStarted {
+        phase: "exhaustive (parallel)",
+        candidate_count: total - 1,
+    });
+
+    let flip_sets = free_variable_flip_sets(solution);
+    let pool = build_worker_pool(max_workers);
+    let worker_count = pool.current_num_threads().max(1);
+    let chunk_size = (total - 1).div_ceil(worker_count).max(1);
+
+    let chunks = (1..total)
+        .step_by(chunk_size)
+        .map(|start| start..(start + chunk_size).min(total))
+        .collect_vec();
+
+    let result = pool.install(|| {
+        chunks
+            .into_par_iter()
+            .find_map_any(|range| exhaustive_chunk(&n_big, smoothies, &flip_sets, range))
+    });
+
+    if result.is_some() {
+        observer.on_event(ProgressEvent::FactorFound);
+    }
+
+    result
+}
+
+/// same per-attempt body as `find_factors_random`'s inner loop, run against one worker's own RNG
+/// stream for a bounded number of attempts at a fixed pressure.
+fn random_chunk<NT: NumberOps>(
+    n: &BigUint,
+    smoothies: &[SmoothNumber<NT>],
+    solution: &Solution,
+    pressure: u32,
+    attempts: usize,
+    rng: &mut impl Rng,
+) -> Option<(BigUint, BigUint)> {
+    let one = BigUint::from_i32(1).unwrap();
+    let mut free_mapping = vec![false; solution.free_variables.len()];
+
+    for _attempt in 0..attempts {
+        free_mapping
+            .iter_mut()
+            .for_each(|position| *position = rng.gen_ratio(1, pressure));
+
+        let inclusion = solution.subsitute(&free_mapping, false);
+
+        let (mut a, mut b) = smoothies
+            .iter()
+            .zip(inclusion.iter())
+            .filter_map(|(a, &b)| if b { Some(a) } else { None })
+            .fold((one.clone(), one.clone()), |acc, item| {
+                let left = acc.0 * &item.number.to_varsize();
+                let right = acc.1
+                    * item
+                        .divisors
+                        .iter()
+                        .map(|&(divisor, power)| BigUint::from(divisor).pow(power as u32))
+                        .product::<BigUint>();
+                (left, right)
+            });
+
+        b = b.sqrt();
+
+        if b > a {
+            std::mem::swap(&mut a, &mut b);
+        }
+
+        if let Some(sol) = test_factorization(n, &a, &b) {
+            return Some(sol);
+        }
+    }
+
+    None
+}
+
+/// multicore counterpart of `find_factors_random`: each worker gets its own `ChaCha8Rng` stream,
+/// seeded deterministically from the caller's `rng` before dispatch (so each worker's sequence of
+/// candidates is reproducible given the caller's seed; when several workers find a factor, which
+/// one is returned depends on scheduling), and searches its own share of the attempt budget at
+/// each pressure level.
+pub fn find_factors_random_parallel<NT: NumberOps + Sync>(
+    n: &NT,
+    smoothies: &[SmoothNumber<NT>],
+    solution: &Solution,
+    rng: &mut impl Rng,
+    max_workers: Option<usize>,
+    observer: &mut impl ProgressObserver,
+) -> Option<(BigUint, BigUint)> {
+    info!("trying parallel random search");
+
+    if let Some(answ) = search_lonelies(n, smoothies, solution) {
+        observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: true });
+        observer.on_event(ProgressEvent::FactorFound);
+        return Some(answ);
+    }
+    observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: false });
+
+    let n_big = n.to_varsize();
+
+    if solution.free_variables.is_empty() {
+        return None;
+    }
+
+    let attempts = 2usize
+        .checked_pow(solution.free_variables.len() as u32)
+        .map(|v| v.min(10_000))
+        .unwrap_or(10_000);
+
+    let limit = solution.free_variables.len();
+    let mut current_limit = limit;
+
+    let pressure_variants = repeat_with(|| {
+        let next_value = current_limit;
+        current_limit /= 2;
+        next_value as u32
+    })
+    .take_while(|&n| n >= 2)
+    .collect_vec();
+
+    let pool = build_worker_pool(max_workers);
+    let worker_count = pool.current_num_threads().max(1);
+
+    for pressure in pressure_variants {
+        debug!("trying 1/{pressure} across {worker_count} workers");
+        observer.on_event(ProgressEvent::SearchStarted {
+            phase: "random (parallel)",
+            candidate_count: attempts,
+        });
+
+        let attempts_per_worker = attempts.div_ceil(worker_count).max(1);
+        let worker_seeds = (0..worker_count).map(|_| rng.gen::<u64>()).collect_vec();
+
+        let result = pool.install(|| {
+            worker_seeds.into_par_iter().find_map_any(|seed| {
+                let mut worker_rng = ChaCha8Rng::seed_from_u64(seed);
+                random_chunk(
+                    &n_big,
+                    smoothies,
+                    solution,
+                    pressure,
+                    attempts_per_worker,
+                    &mut worker_rng,
+                )
+            })
+        });
+
+        if let Some(sol) = result {
+            observer.on_event(ProgressEvent::FactorFound);
+            return Some(sol);
+        }
+    }
+
+    None
+}
+
+/// exhaustive search over every free-variable assignment, visited in Gray-code order so that
+/// consecutive assignments differ by exactly one toggled free variable. Rather than recomputing
+/// `(a, b)` from scratch at every step (as `attempt_factorization` does), we keep running products
+/// `a mod n` and the exact (unreduced) product `b` making up its literal square root, and update
+/// them in O(1) big-int operations per toggle via `flip_sets`.
+///
+/// `a` is only ever consumed modulo `n` downstream, so it is safe to keep it reduced throughout;
+/// toggling a relation off requires dividing it back out of that residue, which takes a modular
+/// inverse. If that inverse doesn't exist, `gcd(number, n)` is itself a nontrivial factor of `n` -
+/// a lucky find we can return immediately instead of continuing the search.
 pub fn find_factor_exhaustive<NT: NumberOps>(
     n: &NT,
     smoothies: &[SmoothNumber<NT>],
     solution: &Solution,
+    observer: &mut impl ProgressObserver,
 ) -> Option<(BigUint, BigUint)> {
     //first, try lonely numbers - maybe, we will find perfect square without multiplying
 
-    println!("using exhaustive search");
+    info!("using exhaustive search");
 
     if let Some(answ) = search_lonelies(n, smoothies, solution) {
+        observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: true });
+        observer.on_event(ProgressEvent::FactorFound);
         return Some(answ);
     }
+    observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: false });
 
     let n = n.to_varsize();
 
@@ -139,17 +478,53 @@ pub fn find_factor_exhaustive<NT: NumberOps>(
 
     let limit = solution.free_variables.len();
 
-    println!("variables for exhaustive search: {limit}");
+    debug!("variables for exhaustive search: {limit}");
 
-    let mut free_mapping = vec![false; solution.free_variables.len()];
+    let total = 1usize << limit;
+    observer.on_event(ProgressEvent::SearchStarted {
+        phase: "exhaustive",
+        candidate_count: total - 1,
+    });
 
-    increase(&mut free_mapping);
+    let flip_sets = free_variable_flip_sets(solution);
 
-    while !is_zero(&free_mapping) {
-        if let Some(sol) = attempt_factorization(&n, smoothies, solution, &free_mapping) {
+    let mut included = vec![false; smoothies.len()];
+    let mut a = ONE.clone();
+    let mut b = ONE.clone();
+
+    for step in 1..total {
+        let bit = step.trailing_zeros() as usize;
+
+        for &idx in &flip_sets[bit] {
+            let number = smoothies[idx].number.to_varsize();
+            let residue = number.modpow(&TWO, &n);
+
+            if included[idx] {
+                let Some(inverse) = mod_inverse_biguint(&number, &n) else {
+                    let gcd = euclid(&number, &n);
+                    observer.on_event(ProgressEvent::FactorFound);
+                    return Some((gcd.clone(), &n / gcd));
+                };
+
+                a = (a * inverse).rem(&n);
+                b /= &residue;
+            } else {
+                a = (a * &number).rem(&n);
+                b *= &residue;
+            }
+
+            included[idx] = !included[idx];
+        }
+
+        observer.on_event(ProgressEvent::CandidatesTried {
+            tried: step,
+            total: total - 1,
+        });
+
+        if let Some(sol) = test_factorization(&n, &a, &b.sqrt()) {
+            observer.on_event(ProgressEvent::FactorFound);
             return Some(sol);
         }
-        increase(&mut free_mapping);
     }
 
     None
@@ -159,14 +534,18 @@ pub fn find_factor_simple<NT: NumberOps>(
     n: &NT,
     smoothies: &[SmoothNumber<NT>],
     solution: &Solution,
+    observer: &mut impl ProgressObserver,
 ) -> Option<(BigUint, BigUint)> {
     //first, try lonely numbers - maybe, we will find perfect square without multiplying
 
-    println!("trying base vector search");
+    info!("trying base vector search");
 
     if let Some(answ) = search_lonelies::<NT>(n, smoothies, solution) {
+        observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: true });
+        observer.on_event(ProgressEvent::FactorFound);
         return Some(answ);
     }
+    observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: false });
 
     let n = n.to_varsize();
 
@@ -179,7 +558,12 @@ pub fn find_factor_simple<NT: NumberOps>(
 
     let limit = solution.free_variables.len();
 
-    println!("variables for base vector search: {limit}");
+    debug!("variables for base vector search: {limit}");
+
+    observer.on_event(ProgressEvent::SearchStarted {
+        phase: "base vector",
+        candidate_count: limit,
+    });
 
     let mut free_mapping = vec![false; solution.free_variables.len()];
 
@@ -189,7 +573,13 @@ pub fn find_factor_simple<NT: NumberOps>(
         }
         free_mapping[i] = true;
 
+        observer.on_event(ProgressEvent::CandidatesTried {
+            tried: i + 1,
+            total: limit,
+        });
+
         if let Some(sol) = attempt_factorization(&n, smoothies, solution, &free_mapping) {
+            observer.on_event(ProgressEvent::FactorFound);
             return Some(sol);
         }
     }
@@ -197,18 +587,27 @@ pub fn find_factor_simple<NT: NumberOps>(
     None
 }
 
+/// same search as `find_factor_exhaustive`, but samples free-variable assignments at random
+/// instead of enumerating them all. `rng` is threaded in by the caller (rather than reaching for
+/// `thread_rng()` internally) so a run that finds a factor can be replayed exactly: seed a
+/// `ChaCha8Rng` with a known value, log that seed, and the same search is reproducible.
 pub fn find_factors_random<NT: NumberOps>(
     n: &NT,
     smoothies: &[SmoothNumber<NT>],
     solution: &Solution,
+    rng: &mut impl Rng,
+    observer: &mut impl ProgressObserver,
 ) -> Option<(BigUint, BigUint)> {
     //first, try lonely numbers - maybe, we will find perfect square without multiplying
 
-    println!("trying random search");
+    info!("trying random search");
 
     if let Some(answ) = search_lonelies(n, smoothies, solution) {
+        observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: true });
+        observer.on_event(ProgressEvent::FactorFound);
         return Some(answ);
     }
+    observer.on_event(ProgressEvent::LonelyCheckCompleted { factor_found: false });
 
     let n = n.to_varsize();
 
@@ -224,8 +623,6 @@ pub fn find_factors_random<NT: NumberOps>(
         .map(|v| v.min(10_000))
         .unwrap_or(10_000);
 
-    let mut rng = thread_rng();
-
     let one = BigUint::from_i32(1).unwrap();
 
     let limit = solution.free_variables.len();
@@ -242,12 +639,22 @@ pub fn find_factors_random<NT: NumberOps>(
     let mut free_mapping = vec![false; solution.free_variables.len()];
 
     for pressure in pressure_variants {
-        println!("trying 1/{}", pressure);
-        for _attempt in 0..attempts {
+        debug!("trying 1/{}", pressure);
+        observer.on_event(ProgressEvent::SearchStarted {
+            phase: "random",
+            candidate_count: attempts,
+        });
+
+        for attempt in 0..attempts {
             free_mapping
                 .iter_mut()
                 .for_each(|position| *position = rng.gen_ratio(1, pressure));
 
+            observer.on_event(ProgressEvent::CandidatesTried {
+                tried: attempt + 1,
+                total: attempts,
+            });
+
             let inclusion = solution.subsitute(&free_mapping, false);
 
             let (mut a, mut b) = smoothies
@@ -275,6 +682,7 @@ pub fn find_factors_random<NT: NumberOps>(
                 continue;
             };
 
+            observer.on_event(ProgressEvent::FactorFound);
             return Some(solution);
         }
     }
@@ -300,32 +708,156 @@ pub fn find_factors_from_pivots<NT: NumberOps>(
 
 #[cfg(test)]
 mod tests {
-    use super::{increase, is_zero};
+    use super::{
+        find_factor_exhaustive_parallel, find_factors_random, free_variable_flip_sets,
+        mod_inverse_biguint, test_factorization,
+    };
+    use crate::progress::NoopObserver;
+    use crate::solver::{Dependency, Solution};
+    use crate::{number_type::NumberOps, sieve::SmoothNumber};
+    use crypto_bigint::U64;
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    macro_rules! set {
+        ( $( $x:expr ),* ) => {{
+            let mut temp_set = HashSet::new();
+            $( temp_set.insert($x); )*
+            temp_set
+        }};
+    }
 
     #[test]
-    fn should_turn_0_into_1() {
-        let mut v = vec![false, false, false];
-        increase(&mut v);
-        assert_eq!(v, vec![true, false, false]);
+    fn mod_inverse_roundtrips() {
+        let value = BigUint::from(7u32);
+        let modulus = BigUint::from(101u32);
+        let inverse = mod_inverse_biguint(&value, &modulus).unwrap();
+        assert_eq!((value * inverse) % &modulus, BigUint::from(1u32));
+    }
+
+    #[test]
+    fn mod_inverse_is_none_without_coprimality() {
+        assert_eq!(
+            mod_inverse_biguint(&BigUint::from(6u32), &BigUint::from(9u32)),
+            None
+        );
+    }
+
+    #[test]
+    fn flip_sets_match_brute_force_substitution() {
+        // variable 0 depends on free variables 1 and 2: dep = var1 XOR var2
+        let solution = Solution {
+            vars: set![0usize, 1, 2],
+            free_variables: set![1usize, 2],
+            lonely_variables: set![],
+            constants: set![],
+            dependencies: vec![Dependency {
+                variable: 0,
+                factors: set![1usize, 2],
+            }],
+        };
+
+        let flip_sets = free_variable_flip_sets(&solution);
+
+        // build up every assignment incrementally via the flip sets and compare against a
+        // brute-force call to `subsitute` for the same assignment. `included` tracks the
+        // *Gray-code* assignment at `step` (flipping bit `trailing_zeros(step)` each time), so the
+        // brute-force substitution vector must be built from `gray_code(step)`, not `step` itself.
+        let mut included = vec![false; solution.vars.len()];
+        for step in 1..4usize {
+            let bit = step.trailing_zeros() as usize;
+            for &idx in &flip_sets[bit] {
+                included[idx] = !included[idx];
+            }
+
+            let gray_code = step ^ (step >> 1);
+            let free_mapping = [gray_code & 1 != 0, gray_code & 2 != 0];
+            let expected = solution.subsitute(&free_mapping, false);
+
+            assert_eq!(included, expected);
+        }
     }
 
     #[test]
-    fn should_apply_carry_bit() {
-        let mut v = vec![true, true, true, false];
-        increase(&mut v);
-        assert_eq!(v, vec![false, false, false, true]);
+    fn random_search_is_deterministic_given_seed() {
+        let n = U64::convert_usize(35);
+        let smoothies = vec![
+            SmoothNumber {
+                number: U64::convert_usize(6),
+                divisors: vec![],
+                large_prime: None,
+            },
+            SmoothNumber {
+                number: U64::convert_usize(29),
+                divisors: vec![],
+                large_prime: None,
+            },
+        ];
+
+        let solution = Solution {
+            vars: set![0usize, 1],
+            free_variables: set![0usize, 1],
+            lonely_variables: set![],
+            constants: set![],
+            dependencies: vec![],
+        };
+
+        let mut rng1 = ChaCha8Rng::seed_from_u64(42);
+        let mut rng2 = ChaCha8Rng::seed_from_u64(42);
+
+        let result1 = find_factors_random(&n, &smoothies, &solution, &mut rng1, &mut NoopObserver);
+        let result2 = find_factors_random(&n, &smoothies, &solution, &mut rng2, &mut NoopObserver);
+
+        assert_eq!(result1, result2);
     }
 
     #[test]
-    fn should_increase() {
-        let mut v = vec![true, false, true];
-        increase(&mut v);
-        assert_eq!(v, vec![false, true, true]);
+    fn parallel_exhaustive_search_finds_a_valid_factor_pair() {
+        let n = U64::convert_usize(35);
+        let smoothies = vec![
+            SmoothNumber {
+                number: U64::convert_usize(6),
+                divisors: vec![],
+                large_prime: None,
+            },
+            SmoothNumber {
+                number: U64::convert_usize(29),
+                divisors: vec![],
+                large_prime: None,
+            },
+        ];
+
+        let solution = Solution {
+            vars: set![0usize, 1],
+            free_variables: set![0usize, 1],
+            lonely_variables: set![],
+            constants: set![],
+            dependencies: vec![],
+        };
+
+        let (a, b) =
+            find_factor_exhaustive_parallel(&n, &smoothies, &solution, Some(2), &mut NoopObserver)
+                .expect("expected a nontrivial factor");
+
+        assert_eq!(&a * &b, BigUint::from(35u32));
+        assert_ne!(a, BigUint::from(1u32));
+        assert_ne!(b, BigUint::from(1u32));
     }
 
     #[test]
-    fn should_be_zero() {
-        let v = vec![false, false, false];
-        assert!(is_zero(&v))
+    fn test_factorization_recovers_shared_factor_when_diff_and_sum_are_trivial() {
+        // n = 21 = 3 * 7; a = 3 and b = 7 each carry one of n's prime factors, but
+        // gcd(n, a - b) and gcd(n, a + b) both come out trivial (1), so only checking a and b
+        // directly recovers the factorization.
+        let n = BigUint::from(21u32);
+        let a = BigUint::from(3u32);
+        let b = BigUint::from(7u32);
+
+        assert_eq!(
+            test_factorization(&n, &a, &b),
+            Some((BigUint::from(7u32), BigUint::from(3u32)))
+        );
     }
 }
@@ -5,16 +5,50 @@ use crate::number_type::NumberOps;
 use crate::sieve::SmoothNumber;
 use crypto_bigint::UInt;
 use itertools::Itertools;
+use log::{debug, info};
 use num_bigint::BigUint;
 use num_integer::Roots;
 use num_traits::ToPrimitive;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
+use crate::progress::NoopObserver;
 use crate::{
-    factor_building::{find_factor_exhaustive, find_factor_simple, find_factors_random},
-    numbers::{build_factor_base, small_eratosphenes},
-    sieve::{compute_b_limit, BlockSieve, LogSieve, TestDivisionSieve},
+    factor_building::{
+        find_factor_exhaustive_parallel, find_factor_simple, find_factors_random_parallel,
+    },
+    numbers::{build_factor_base, is_prime},
+    sieve::{
+        combine_partial_relations, compute_b_limit, BlockSieve, LogSieve, MpqsSieve,
+        TestDivisionSieve,
+    },
     solver::{produce_solution, CongruenceSystem},
 };
+
+/// factor-base size from which `run_factor` sieves with self-initializing MPQS instead of
+/// `LogSieve`'s single polynomial `x^2 - n`
+const MPQS_FACTOR_BASE_THRESHOLD: usize = 20;
+
+/// half-width of the sieve interval `MpqsSieve` sweeps per polynomial
+const MPQS_SIEVE_HALF_WIDTH: usize = 30_000;
+
+/// the sieve backend `run_factor` drives through its retry loop: `LogSieve` keeps sieving the one
+/// polynomial `x^2 - n` in successive blocks above `sqrt(n)`, with roots computed once in `new`,
+/// while `Mpqs` uses self-initialization to move to the next polynomial without re-solving the CRT
+enum ActiveSieve<NT: NumberOps> {
+    Log(LogSieve<NT>),
+    Mpqs(MpqsSieve<NT>),
+}
+
+impl<NT: NumberOps> ActiveSieve<NT> {
+    fn run(&mut self, total_numbers: usize) -> Vec<SmoothNumber<NT>> {
+        match self {
+            ActiveSieve::Log(sieve) => sieve.run(total_numbers),
+            ActiveSieve::Mpqs(sieve) => sieve.run(total_numbers),
+        }
+    }
+}
+
 fn gaussian_multistage<NT: NumberOps>(
     n: &NT,
     table: Vec<SmoothNumber<NT>>,
@@ -33,25 +67,58 @@ fn gaussian_multistage<NT: NumberOps>(
     #[cfg(feature = "verbose")]
     println!("linear system {:?}", solution);
 
-    println!(
+    info!(
         "number of dependencies in solution: {}",
         solution.dependencies.len()
     );
 
-    println!("built solution dependencies, searching for factors");
-
-    find_factor_simple::<NT>(n, &table, &solution)
-        .or_else(|| find_factors_random(n, &table, &solution))
-        .or_else(|| find_factor_exhaustive(n, &table, &solution))
+    debug!("built solution dependencies, searching for factors");
+
+    let random_search_seed = thread_rng().gen::<u64>();
+    info!("random search seed: {random_search_seed}");
+    let mut random_search_rng = ChaCha8Rng::seed_from_u64(random_search_seed);
+
+    find_factor_simple::<NT>(n, &table, &solution, &mut NoopObserver)
+        .or_else(|| {
+            find_factors_random_parallel(
+                n,
+                &table,
+                &solution,
+                &mut random_search_rng,
+                None,
+                &mut NoopObserver,
+            )
+        })
+        .or_else(|| find_factor_exhaustive_parallel(n, &table, &solution, None, &mut NoopObserver))
 }
 
+/// how much column/row excess `filter`'s structured elimination pre-pass is required to leave
+/// behind, so `fast_pivot`'s `x_labels.len() > rows.len()` invariant always still holds afterward
+const FILTER_MIN_EXCESS: usize = 5;
+
+/// past this many surviving rows, `fast_pivot`'s dense O(rows^3)-ish elimination gets slow enough
+/// that the sparse Krylov-subspace `block_lanczos` solver pays for itself instead
+const BLOCK_LANCZOS_ROW_THRESHOLD: usize = 500;
+
 fn pivot_search<NT: NumberOps>(
     n: &NT,
     table: Vec<SmoothNumber<NT>>,
-    mut system: CongruenceSystem,
+    system: CongruenceSystem,
 ) -> Option<(BigUint, BigUint)> {
-    println!("using fast pivot algorithm");
-    let vectors = system.fast_pivot();
+    let (mut system, _provenance) = system.filter(FILTER_MIN_EXCESS);
+    println!(
+        "structured elimination pre-pass left {} rows",
+        system.row_count()
+    );
+
+    let vectors = if system.row_count() > BLOCK_LANCZOS_ROW_THRESHOLD {
+        println!("system is too large for dense pivoting, falling back to block Lanczos");
+        system.block_lanczos()
+    } else {
+        println!("using fast pivot algorithm");
+        system.fast_pivot()
+    };
+
     println!("produced {} candidates", vectors.len());
     if let Some(answ) = find_factors_from_pivots(n, &table, &vectors) {
         return Some(answ);
@@ -61,11 +128,7 @@ fn pivot_search<NT: NumberOps>(
 }
 
 fn run_factor<NT: NumberOps>(n: &NT, prime_bound: usize) -> Option<(BigUint, BigUint)> {
-    let primes = small_eratosphenes(prime_bound);
-
-    println!("primes until bound: {}", primes.len());
-
-    let factor_base = build_factor_base(primes, n);
+    let factor_base = build_factor_base(n, prime_bound);
 
     println!("built factor base of size {}", factor_base.len(),);
 
@@ -89,7 +152,17 @@ fn run_factor<NT: NumberOps>(n: &NT, prime_bound: usize) -> Option<(BigUint, Big
 
     let mut table = vec![];
 
-    let mut sieve = LogSieve::new(*n, factor_base.clone());
+    let mut sieve = if factor_base.len() >= MPQS_FACTOR_BASE_THRESHOLD {
+        println!("factor base is large enough, using self-initializing MPQS");
+        ActiveSieve::Mpqs(MpqsSieve::new(*n, factor_base.clone(), MPQS_SIEVE_HALF_WIDTH))
+    } else {
+        ActiveSieve::Log(LogSieve::new(*n, factor_base.clone()))
+    };
+
+    // `BlockSieve` is the only sieve here that also reports partial (large-prime) relations
+    // alongside fully-smooth ones; run it after `sieve` in every attempt so those partials exist to be
+    // folded together by `combine_partial_relations` below.
+    let mut block_sieve = BlockSieve::new(*n, factor_base.clone());
 
     for _ in 0..NUM_ATTEMPTS {
         let sieving_limit = usize::max(
@@ -100,9 +173,12 @@ fn run_factor<NT: NumberOps>(n: &NT, prime_bound: usize) -> Option<(BigUint, Big
         println!("need about {sieving_limit} numbers");
 
         let mut additional_table = sieve.run(sieving_limit.saturating_sub(table.len()));
-
         table.append(&mut additional_table);
 
+        let mut partial_table = block_sieve.run(factor_base.len());
+        table.append(&mut partial_table);
+        table = combine_partial_relations(n, table);
+
         println!("done collecting, building solution");
 
         #[cfg(feature = "verbose")]
@@ -125,11 +201,9 @@ fn run_factor<NT: NumberOps>(n: &NT, prime_bound: usize) -> Option<(BigUint, Big
         #[cfg(feature = "verbose")]
         println!("---------");
 
-        if cfg!(feature = "multistage") {
-            if let Some(answ) = gaussian_multistage(n, table.clone(), system) {
-                return Some(answ);
-            }
-        } else if let Some(answ) = pivot_search(n, table.clone(), system) {
+        if let Some(answ) = pivot_search(n, table.clone(), system.clone()) {
+            return Some(answ);
+        } else if let Some(answ) = gaussian_multistage(n, table.clone(), system) {
             return Some(answ);
         }
 
@@ -196,15 +270,9 @@ where
     Err("could not factorize".to_string())
 }
 
-pub fn factorize(number_repr: String) -> Result<Vec<BigUint>, String> {
-    let n = BigUint::from_str(&number_repr).map_err(|e| e.to_string())?;
-
-    println!("n: {}", n);
-
-    println!("base 10 digits: {}", n.to_string().len());
-
-    println!("bit size: {}", n.bits());
-
+/// splits a composite `n` into two nontrivial factors `(a, b)` with `a * b == n`, using whichever
+/// backend fits `n`'s size. Does not recurse any further than that single split.
+fn split_once(n: &BigUint) -> Result<(BigUint, BigUint), String> {
     let bytes = n.to_bytes_be();
 
     if bytes.len() >= 64 {
@@ -212,40 +280,84 @@ pub fn factorize(number_repr: String) -> Result<Vec<BigUint>, String> {
     }
 
     if bytes.len() < 4 {
-        return trial_divide(n.to_u64().unwrap() as usize)
-            .map(|ok| ok.into_iter().map(BigUint::from).collect_vec());
+        let mut divisors = trial_divide(n.to_u64().unwrap() as usize)?;
+        let first = BigUint::from(divisors.remove(0));
+        let rest = divisors.into_iter().map(BigUint::from).product();
+        return Ok((first, rest));
     }
 
-    if bytes.len() < 8 {
-        return run_factorization_generic::<1>(bytes);
-    }
+    let split = if bytes.len() < 8 {
+        run_factorization_generic::<1>(bytes)?
+    } else if bytes.len() < 16 {
+        run_factorization_generic::<2>(bytes)?
+    } else if bytes.len() < 32 {
+        run_factorization_generic::<4>(bytes)?
+    } else {
+        run_factorization_generic::<8>(bytes)?
+    };
+
+    let mut split = split.into_iter();
+    let a = split.next().unwrap();
+    let b = split.next().unwrap();
+    Ok((a, b))
+}
 
-    if bytes.len() < 16 {
-        return run_factorization_generic::<2>(bytes);
+/// recursively factors `n` down to its complete prime factorization (with multiplicity), using
+/// a Miller-Rabin pretest to stop recursing as soon as a branch is prime instead of always
+/// reaching for the quadratic sieve.
+fn factorize_complete(n: &BigUint) -> Result<Vec<BigUint>, String> {
+    if n <= &BigUint::from(1u32) {
+        return Ok(vec![]);
     }
 
-    if bytes.len() < 32 {
-        return run_factorization_generic::<4>(bytes);
+    if is_prime(n) {
+        return Ok(vec![n.clone()]);
     }
 
-    run_factorization_generic::<8>(bytes)
+    let (a, b) = split_once(n)?;
+
+    let mut result = factorize_complete(&a)?;
+    result.extend(factorize_complete(&b)?);
+    result.sort();
+
+    Ok(result)
+}
+
+pub fn factorize(number_repr: String) -> Result<Vec<BigUint>, String> {
+    let n = BigUint::from_str(&number_repr).map_err(|e| e.to_string())?;
+
+    println!("n: {}", n);
+
+    println!("base 10 digits: {}", n.to_string().len());
+
+    println!("bit size: {}", n.bits());
+
+    factorize_complete(&n)
 }
 
+/// full trial division bounded by `sqrt(number)`; any cofactor left over after that is itself
+/// prime and is reported as the final factor.
 fn trial_divide(number: usize) -> Result<Vec<usize>, String> {
     let mut to_factor = number;
 
     let mut divisors = vec![];
 
-    for i in 2..number {
+    let mut i = 2usize;
+    while i.saturating_mul(i) <= to_factor {
         while to_factor % i == 0 {
             divisors.push(i);
             to_factor /= i;
         }
+        i += 1;
     }
 
     if divisors.is_empty() {
-        Err("number is prime (tested all divisors up to n)".to_string())
-    } else {
-        Ok(divisors)
+        return Err("number is prime (tested all divisors up to sqrt(n))".to_string());
     }
+
+    if to_factor > 1 {
+        divisors.push(to_factor);
+    }
+
+    Ok(divisors)
 }
@@ -11,6 +11,7 @@ mod numbers;
 
 mod factor_building;
 mod factorization;
+mod progress;
 mod sieve;
 mod solver;
 
@@ -25,6 +25,10 @@ where
 
     fn to_varsize(self) -> BigUint;
 
+    /// inverse of `to_varsize`: pads `value` up to this type's byte width and reads it back.
+    /// Panics if `value` doesn't fit.
+    fn from_varsize(value: &BigUint) -> Self;
+
     fn to_usize(self) -> usize;
 
     fn wrapping_add(&self, other: &Self) -> Self;
@@ -82,6 +86,20 @@ macro_rules! impl_number_ops {
                 BigUint::from_bytes_be(&buf)
             }
 
+            fn from_varsize(value: &BigUint) -> Self {
+                let total_bytes = $size * std::mem::size_of::<u64>();
+                let bytes = value.to_bytes_be();
+                assert!(
+                    bytes.len() <= total_bytes,
+                    "value does not fit into this integer size"
+                );
+
+                let mut padded = vec![0u8; total_bytes - bytes.len()];
+                padded.extend_from_slice(&bytes);
+
+                <$t>::from_be_slice(&padded)
+            }
+
             #[inline]
             fn add_usize(self, other: usize) -> Self {
                 self.wrapping_add(&Self::convert_usize(other))
@@ -29,8 +29,72 @@ pub fn small_eratosphenes(upper_limit: usize) -> Vec<usize> {
     result
 }
 
+/// same primes as `small_eratosphenes`, but bounds memory to `O(sqrt(upper_limit) + segment_size)`
+/// by sieving `[2, upper_limit]` in fixed-size windows instead of allocating one giant bool buffer.
+pub fn segmented_eratosphenes(upper_limit: usize, segment_size: usize) -> Vec<usize> {
+    assert!(segment_size > 0, "segment size must be positive");
+
+    if upper_limit < 2 {
+        return vec![];
+    }
+
+    let sqrt_limit = (upper_limit as f64).sqrt().ceil() as usize;
+
+    let base_primes = small_eratosphenes(sqrt_limit);
+
+    let mut result = base_primes.clone();
+
+    if sqrt_limit >= upper_limit {
+        result.retain(|&p| p <= upper_limit);
+        return result;
+    }
+
+    let mut lo = sqrt_limit + 1;
+
+    while lo <= upper_limit {
+        let hi = usize::min(lo + segment_size, upper_limit + 1);
+
+        let mut is_composite = vec![false; hi - lo];
+
+        for &prime in &base_primes {
+            let lower_bound = match prime.checked_mul(prime) {
+                Some(square) => square,
+                None => continue,
+            };
+
+            let start = if lower_bound >= lo {
+                lower_bound
+            } else {
+                lo.div_ceil(prime) * prime
+            };
+
+            let mut multiple = start;
+            while multiple < hi {
+                is_composite[multiple - lo] = true;
+                multiple += prime;
+            }
+        }
+
+        result.extend(
+            (lo..hi)
+                .zip(is_composite.iter())
+                .filter(|(_, &composite)| !composite)
+                .map(|(number, _)| number),
+        );
+
+        lo = hi;
+    }
+
+    result
+}
+
 pub fn legendre(a: BigUint, p: BigUint) -> isize {
-    use num_traits::Zero;
+    use num_traits::{ToPrimitive, Zero};
+
+    if let (Some(a_native), Some(p_native)) = (a.to_u64(), p.to_u64()) {
+        return legendre_native(a_native as usize, p_native as usize);
+    }
+
     let power = (p.clone() - BigUint::from(1usize)) / BigUint::from(2usize);
 
     let res = a.modpow(&power, &p);
@@ -45,6 +109,78 @@ pub fn legendre(a: BigUint, p: BigUint) -> isize {
     1
 }
 
+/// `legendre`, specialized to the case where both operands fit in a machine word (the common
+/// case: `p` is a factor-base prime), avoiding per-call `BigUint` allocation.
+fn legendre_native(a: usize, p: usize) -> isize {
+    let reduced = a % p;
+    let power = (p - 1) / 2;
+
+    let res = modpow(reduced, power, p);
+
+    if res == p - 1 {
+        return -1;
+    }
+
+    if res == 0 {
+        return 0;
+    }
+    1
+}
+
+const MILLER_RABIN_WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// strong probable-prime (Miller-Rabin) test. The witness set above is a known deterministic
+/// set for any `n < 3.18 * 10^23`; for larger `n` it is not a proof, but the chance of a composite
+/// slipping through all twelve witnesses is astronomically small.
+pub fn is_prime(n: &BigUint) -> bool {
+    let zero = BigUint::from(0u32);
+    let one = BigUint::from(1u32);
+    let two = BigUint::from(2u32);
+    let three = BigUint::from(3u32);
+
+    if n < &two {
+        return false;
+    }
+    if n == &two || n == &three {
+        return true;
+    }
+    if n.clone() % &two == zero {
+        return false;
+    }
+
+    let n_minus_one = n - &one;
+    let mut d = n_minus_one.clone();
+    let mut s: u32 = 0;
+    while d.clone() % &two == zero {
+        d /= &two;
+        s += 1;
+    }
+
+    'witness: for &witness in &MILLER_RABIN_WITNESSES {
+        let a = BigUint::from(witness);
+        if &a >= n {
+            continue;
+        }
+
+        let mut x = a.modpow(&d, n);
+
+        if x == one || x == n_minus_one {
+            continue;
+        }
+
+        for _ in 0..s - 1 {
+            x = x.modpow(&two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
 pub fn gcd(a: usize, b: usize) -> usize {
     if b == 0 {
         a
@@ -53,6 +189,72 @@ pub fn gcd(a: usize, b: usize) -> usize {
     }
 }
 
+/// extended Euclidean algorithm: returns `(gcd, x, y)` such that `a*x + b*y == gcd`.
+pub fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x1, y1) = ext_gcd(b, a % b);
+        (g, y1, x1 - (a / b) * y1)
+    }
+}
+
+/// modular inverse of `a` mod `m`, or `None` when `gcd(a, m) != 1`.
+pub fn mod_inverse(a: usize, m: usize) -> Option<usize> {
+    let (g, x, _) = ext_gcd(a as i128, m as i128);
+    if g != 1 && g != -1 {
+        return None;
+    }
+    let m = m as i128;
+    // bring x (and a sign-flipped gcd) back into [0, m)
+    Some(((x * g).rem_euclid(m)) as usize)
+}
+
+/// Chinese Remainder Theorem over pairwise-coprime moduli: given `(residue, modulus)` pairs,
+/// returns `x === residue_i (mod modulus_i)` for every pair, reduced mod the product of all
+/// moduli. Returns `None` if the moduli are not pairwise coprime.
+pub fn crt(residues: &[(usize, usize)]) -> Option<usize> {
+    let mut acc_residue = 0i128;
+    let mut acc_modulus = 1i128;
+
+    for &(residue, modulus) in residues {
+        let modulus = modulus as i128;
+
+        let (g, p, q) = ext_gcd(acc_modulus, modulus);
+        if g != 1 {
+            return None;
+        }
+
+        let combined_modulus = acc_modulus * modulus;
+
+        let combined = (acc_residue * modulus * q + residue as i128 * acc_modulus * p)
+            .rem_euclid(combined_modulus);
+
+        acc_residue = combined;
+        acc_modulus = combined_modulus;
+    }
+
+    Some(acc_residue as usize)
+}
+
+/// Hensel-lifts a root `root` of `x^2 === n (mod current_power)` to a root of
+/// `x^2 === n (mod current_power * p)`, given the underlying prime `p`.
+///
+/// Formula: `r' = r - (r^2 - n) * inv(2r) (mod p^{k+1})`. Returns `None` when `2*root` has no
+/// inverse mod the lifted modulus (this only happens for `p == 2`, which must be lifted with the
+/// dedicated rules for powers of two instead).
+pub fn hensel_lift(n: usize, p: usize, current_power: usize, root: usize) -> Option<usize> {
+    let next_power = current_power * p;
+
+    let inv = mod_inverse((2 * root) % next_power, next_power)?;
+
+    let residual = ((root as i128) * (root as i128) - n as i128).rem_euclid(next_power as i128);
+
+    let delta = (residual as usize * inv) % next_power;
+
+    Some((root + next_power - delta) % next_power)
+}
+
 fn upcast_modpow(x: usize, e: usize, m: usize) -> usize {
     BigUint::modpow(&BigUint::from(x), &BigUint::from(e), &BigUint::from(m))
         .to_u64_digits()
@@ -60,6 +262,40 @@ fn upcast_modpow(x: usize, e: usize, m: usize) -> usize {
         .unwrap_or_default() as usize
 }
 
+/// `(a * b) % m` via a `u128` intermediate product, so it stays correct without ever touching
+/// the heap, as long as `m` fits in a `u64`.
+#[inline]
+pub(crate) fn mulmod(a: usize, b: usize, m: usize) -> usize {
+    ((a as u128 * b as u128) % m as u128) as usize
+}
+
+/// square-and-multiply modular exponentiation entirely in machine words. Falls back to the
+/// `BigUint` path on the (practically unreachable, since `usize` never exceeds `u64`) case where
+/// `m` doesn't fit in a `u64`.
+fn modpow(base: usize, exp: usize, modulus: usize) -> usize {
+    if modulus as u128 > u64::MAX as u128 {
+        return upcast_modpow(base, exp, modulus);
+    }
+
+    if modulus == 1 {
+        return 0;
+    }
+
+    let mut result = 1usize;
+    let mut base = base % modulus;
+    let mut exp = exp;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mulmod(result, base, modulus);
+        }
+        exp >>= 1;
+        base = mulmod(base, base, modulus);
+    }
+
+    result
+}
+
 /// finds x from x^2 === n (mod p)
 #[allow(non_snake_case)]
 pub fn tonelli_shanks(n: usize, p: usize) -> Option<usize> {
@@ -76,17 +312,17 @@ pub fn tonelli_shanks(n: usize, p: usize) -> Option<usize> {
 
     let mut z_value = 2;
     loop {
-        if upcast_modpow(z_value, (p - 1) / 2, p) == p - 1 {
+        if modpow(z_value, (p - 1) / 2, p) == p - 1 {
             break;
         }
         z_value += 1;
     }
 
     let mut M = s;
-    let mut c = upcast_modpow(z_value, q, p);
-    let mut t = upcast_modpow(n, q, p);
+    let mut c = modpow(z_value, q, p);
+    let mut t = modpow(n, q, p);
 
-    let mut R = upcast_modpow(n, (q + 1) / 2, p);
+    let mut R = modpow(n, (q + 1) / 2, p);
 
     loop {
         if t == 0 {
@@ -99,26 +335,42 @@ pub fn tonelli_shanks(n: usize, p: usize) -> Option<usize> {
 
         let mut i = 1;
         while i < M {
-            if upcast_modpow(t, 2usize.pow(i), p) == 1 {
+            if modpow(t, 2usize.pow(i), p) == 1 {
                 break;
             }
 
             i += 1;
         }
 
-        let b = upcast_modpow(c, 2usize.pow(M - i - 1), p);
+        let b = modpow(c, 2usize.pow(M - i - 1), p);
 
         M = i;
-        c = upcast_modpow(b, 2, p);
-        t = t * b * b % p;
-        R = R * b % p;
+        c = mulmod(b, b, p);
+        t = mulmod(mulmod(t, b, p), b, p);
+        R = mulmod(R, b, p);
     }
 }
 
-pub fn build_factor_base<NT: NumberOps>(primes: Vec<usize>, n: &NT) -> Vec<usize> {
-    primes
+/// cache-sized segment width used when sieving the factor base directly from `n` and `b_limit`,
+/// so memory stays bounded even when `b_limit` reaches the tens of millions.
+const FACTOR_BASE_SEGMENT_SIZE: usize = 1 << 16;
+
+/// sieves every prime `p <= b_limit` with `segmented_eratosphenes`, then keeps only those for
+/// which `n` is a quadratic residue mod `p` (Euler's criterion via `modpow`; `p == 2` always
+/// passes). Primes failing this test can never divide `x^2 - n` and only waste Tonelli-Shanks and
+/// sieving work, so filtering them here shrinks `roots` and every inner sieve loop built from the
+/// returned factor base.
+pub fn build_factor_base<NT: NumberOps>(n: &NT, b_limit: usize) -> Vec<usize> {
+    use num_traits::ToPrimitive;
+
+    segmented_eratosphenes(b_limit, FACTOR_BASE_SEGMENT_SIZE)
         .into_iter()
-        .filter(|&prime| prime == 2 || legendre(n.to_varsize(), BigUint::from(prime)) == 1)
+        .filter(|&prime| {
+            prime == 2 || {
+                let residue = (n.to_varsize() % prime).to_u64().unwrap() as usize;
+                legendre_native(residue, prime) == 1
+            }
+        })
         .collect_vec()
 }
 
@@ -156,11 +408,57 @@ pub fn trial_divide<NT: NumberOps>(n: &NT, prime_table: &[usize]) -> Option<Dens
     }
 }
 
+/// `trial_divide`, specialized to a raw `BigUint` accumulator instead of a `NumberOps` type.
+/// MPQS polynomial values don't necessarily fit the `NT` being factored (they're built from `a`,
+/// `b`, `c` directly rather than reduced mod `n`), so the sieve needs this standalone version.
+pub fn trial_divide_biguint(value: &BigUint, prime_table: &[usize]) -> Option<DenseMultiplierMap> {
+    use num_integer::Integer;
+    use num_traits::Zero;
+
+    let mut result = vec![];
+
+    let mut n = value.clone();
+    let one = BigUint::from(1u32);
+
+    'outer: for &prime in prime_table {
+        let prime_big = BigUint::from(prime);
+        loop {
+            let (d, r) = n.div_rem(&prime_big);
+            if !r.is_zero() {
+                break;
+            }
+
+            match result.last_mut() {
+                Some((p, exp)) if *p == prime => {
+                    *exp += 1;
+                }
+                _ => result.push((prime, 1)),
+            }
+
+            n = d;
+
+            if n == one {
+                break 'outer;
+            }
+        }
+    }
+
+    if n == one {
+        Some(result)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use num_bigint::BigUint;
+    use num_traits::ToPrimitive;
 
-    use super::tonelli_shanks;
+    use super::{
+        build_factor_base, crt, hensel_lift, is_prime, mod_inverse, segmented_eratosphenes,
+        small_eratosphenes, tonelli_shanks, trial_divide_biguint,
+    };
 
     //upcast modpow relies on that
     #[test]
@@ -177,4 +475,91 @@ mod tests {
     fn should_solve_example() {
         assert_eq!(tonelli_shanks(5, 41), Some(28))
     }
+
+    #[test]
+    fn segmented_sieve_matches_simple_sieve() {
+        assert_eq!(segmented_eratosphenes(10_000, 128), small_eratosphenes(10_000));
+    }
+
+    #[test]
+    fn segmented_sieve_handles_small_limits() {
+        assert_eq!(segmented_eratosphenes(1, 128), Vec::<usize>::new());
+        assert_eq!(segmented_eratosphenes(2, 128), vec![2]);
+    }
+
+    #[test]
+    fn recognizes_small_primes_and_composites() {
+        for &p in &[2u32, 3, 5, 7, 97, 7919] {
+            assert!(is_prime(&BigUint::from(p)), "{p} should be prime");
+        }
+
+        for &c in &[1u32, 4, 6, 9, 100, 7921] {
+            assert!(!is_prime(&BigUint::from(c)), "{c} should be composite");
+        }
+    }
+
+    #[test]
+    fn recognizes_large_prime() {
+        // 2^61 - 1, a Mersenne prime
+        assert!(is_prime(&BigUint::from(2_305_843_009_213_693_951u64)));
+    }
+
+    #[test]
+    fn mod_inverse_roundtrips() {
+        let inv = mod_inverse(3, 11).unwrap();
+        assert_eq!((3 * inv) % 11, 1);
+    }
+
+    #[test]
+    fn mod_inverse_is_none_without_coprimality() {
+        assert_eq!(mod_inverse(6, 9), None);
+    }
+
+    #[test]
+    fn hensel_lift_produces_valid_root() {
+        // 3^2 === 9 (mod 13); lift a root of x^2 === 9 (mod 13) to mod 169
+        let root = tonelli_shanks(9, 13).unwrap();
+        assert_eq!((root * root) % 13, 9);
+
+        let lifted = hensel_lift(9, 13, 13, root).unwrap();
+        assert_eq!((lifted * lifted) % 169, 9);
+    }
+
+    #[test]
+    fn crt_combines_residues() {
+        // x === 2 (mod 3), x === 3 (mod 5), x === 2 (mod 7) -> x === 23 (mod 105)
+        let x = crt(&[(2, 3), (3, 5), (2, 7)]).unwrap();
+        assert_eq!(x, 23);
+    }
+
+    #[test]
+    fn trial_divide_biguint_matches_known_factorization() {
+        let value = BigUint::from(2u32 * 2 * 3 * 7 * 7);
+        let divisors = trial_divide_biguint(&value, &[2, 3, 5, 7]).unwrap();
+        assert_eq!(divisors, vec![(2, 2), (3, 1), (7, 2)]);
+    }
+
+    #[test]
+    fn trial_divide_biguint_fails_outside_factor_base() {
+        let value = BigUint::from(2u32 * 11);
+        assert_eq!(trial_divide_biguint(&value, &[2, 3, 5]), None);
+    }
+
+    #[test]
+    fn build_factor_base_keeps_only_quadratic_residues() {
+        use crate::number_type::NumberOps;
+        use crypto_bigint::U64;
+
+        let n = U64::convert_usize(41);
+        let factor_base = build_factor_base(&n, 20);
+
+        assert!(factor_base.contains(&2));
+        for &prime in &factor_base {
+            if prime == 2 {
+                continue;
+            }
+            let residue = (n.to_varsize() % prime).to_u64().unwrap() as usize;
+            assert!(tonelli_shanks(residue, prime).is_some());
+        }
+    }
 }
@@ -0,0 +1,36 @@
+/// Structured events emitted by the search functions in [`crate::factor_building`]. Callers
+/// embedding this crate as a library can implement [`ProgressObserver`] to drive a progress bar,
+/// support cancellation, or stay silent, instead of inheriting the crate's own stdout output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// a search phase started; `candidate_count` is the size of the space being searched
+    /// (`2^free_variables - 1` assignments for exhaustive search, free variables for base-vector
+    /// search, attempts per pressure level for random search)
+    SearchStarted {
+        phase: &'static str,
+        candidate_count: usize,
+    },
+    /// the lonely-number shortcut was checked before the main search began
+    LonelyCheckCompleted { factor_found: bool },
+    /// `tried` out of `total` candidates have been attempted so far in the current phase
+    CandidatesTried { tried: usize, total: usize },
+    /// a nontrivial factor was found and the search is about to return
+    FactorFound,
+}
+
+pub trait ProgressObserver {
+    fn on_event(&mut self, event: ProgressEvent);
+}
+
+/// observer that does nothing; the default for callers that don't care about progress
+pub struct NoopObserver;
+
+impl ProgressObserver for NoopObserver {
+    fn on_event(&mut self, _event: ProgressEvent) {}
+}
+
+impl<F: FnMut(ProgressEvent)> ProgressObserver for F {
+    fn on_event(&mut self, event: ProgressEvent) {
+        self(event)
+    }
+}
@@ -3,12 +3,15 @@ use std::{iter::repeat_with, ops::Rem, sync::atomic::AtomicUsize, time::Instant}
 
 use itertools::Itertools;
 
+use num_bigint::{BigInt, BigUint};
 use num_traits::{Pow, ToPrimitive};
 use rayon::ThreadPoolBuilder;
 
 use crate::{
+    factor_building::mod_inverse_biguint,
     number_type::NumberOps,
-    numbers::{tonelli_shanks, trial_divide},
+    numbers::{is_prime, mod_inverse, mulmod, tonelli_shanks, trial_divide, trial_divide_biguint},
+    solver::{combine_large_primes, PartialRelation},
 };
 
 pub fn compute_b_limit<NT: NumberOps>(n: &NT) -> usize {
@@ -25,6 +28,11 @@ pub fn compute_b_limit<NT: NumberOps>(n: &NT) -> usize {
 pub struct SmoothNumber<NT> {
     pub number: NT,
     pub divisors: Vec<(usize, usize)>,
+    /// set when this is a *partial* relation: the accumulator didn't fully factor over the
+    /// factor base, but what's left is a single prime below `B^2`. Partials are combined in
+    /// pairs sharing the same large prime (see [`combine_partial_relations`]) before being fed
+    /// to the solver; a relation with `large_prime: None` is already fully smooth.
+    pub large_prime: Option<usize>,
 }
 
 pub type SmoothiesVec<NT> = Vec<SmoothNumber<NT>>;
@@ -65,6 +73,7 @@ impl<NT: NumberOps> TestDivisionSieve<NT> {
                 result.push(SmoothNumber {
                     number: self.next_number,
                     divisors: mapping,
+                    large_prime: None,
                 });
                 numbers_to_find -= 1;
 
@@ -279,29 +288,69 @@ impl<NT: NumberOps> BlockSieve<NT> {
             }
         }
 
+        let large_prime_bound = {
+            let largest = NT::convert_usize(*self.factor_base.last().unwrap());
+            largest.wrapping_mul(&largest)
+        };
+
         block
             .into_iter()
             .filter_map(|item| {
-                if &item.accumulator != NT::one() {
-                    return None;
+                if &item.accumulator == NT::one() {
+                    #[cfg(feature = "verbose")]
+                    println!("found number {}", item.original_number.to_varsize());
+
+                    return Some(SmoothNumber {
+                        number: item.original_number,
+                        divisors: item.factorization,
+                        large_prime: None,
+                    });
                 }
 
-                #[cfg(feature = "verbose")]
-                println!("found number {}", item.original_number.to_varsize());
+                // not fully smooth, but if what's left over is a single prime within B^2 of the
+                // factor base, keep it as a partial relation instead of throwing it away: it can
+                // still be combined with another partial sharing the same large prime later
+                if item.accumulator < large_prime_bound && is_prime(&item.accumulator.to_varsize())
+                {
+                    let large_prime = item.accumulator.to_varsize().to_u64().unwrap() as usize;
+
+                    #[cfg(feature = "verbose")]
+                    println!(
+                        "found partial relation {} with large prime {large_prime}",
+                        item.original_number.to_varsize()
+                    );
+
+                    return Some(SmoothNumber {
+                        number: item.original_number,
+                        divisors: item.factorization,
+                        large_prime: Some(large_prime),
+                    });
+                }
 
-                Some(SmoothNumber {
-                    number: item.original_number,
-                    divisors: item.factorization,
-                })
+                None
             })
             .collect_vec()
     }
 }
 
+/// an extra root of `x^2 === n (mod p^power)` for `power >= 2`, Hensel-lifted from the
+/// first-power root. Sieving these in as well as the first-power roots gives numbers divisible
+/// by `p^power` their full `ln(p)` credit instead of just one.
+struct PrimePowerRoot {
+    modulus: usize,
+    root1: usize,
+    root2: usize,
+    log_p: f64,
+}
+
 pub struct LogSieve<NT: NumberOps> {
     n: NT,
     factor_base: Vec<usize>,
     roots: Vec<Option<(usize, usize)>>,
+    power_roots: Vec<PrimePowerRoot>,
+    /// extra `ln(2)` credit applied at every odd `x` when `n === 1 (mod 8)`, approximating
+    /// the contribution of higher powers of two without tracking all four roots mod `2^k`.
+    two_power_log: Option<f64>,
     block_size: usize,
     next_block: NT,
     log_treshold: f64,
@@ -309,13 +358,21 @@ pub struct LogSieve<NT: NumberOps> {
 
 const LOGSIEVE_BLOCK_SIZE: usize = 60_000;
 
+/// width of the cache-sized sub-segments `search_block` buckets large primes into. Primes whose
+/// stride exceeds this touch the `logs` array only a handful of times with huge strides, which
+/// thrashes the cache if sieved directly; bucketing them by landing segment instead keeps each
+/// pass over `logs` local to one L2-resident slice.
+const LOGSIEVE_SUBSEGMENT_SIZE: usize = 16_384;
+
 impl<NT: NumberOps> LogSieve<NT> {
     //initalization is the same, what differs is the search algorithm
     pub fn new(n: NT, factor_base: Vec<usize>) -> Self {
+        let n_varsize = n.to_varsize();
+
         let roots = factor_base
             .iter()
             .map(|&factor| {
-                let n = n.to_varsize().rem(factor).to_u64().unwrap() as usize;
+                let n = n_varsize.clone().rem(factor).to_u64().unwrap() as usize;
 
                 let Some(s1) = tonelli_shanks(n, factor) else{
                     return None;
@@ -334,7 +391,16 @@ impl<NT: NumberOps> LogSieve<NT> {
             factor_base.last().unwrap() * 2,
         );
 
-        let n_f = n.to_varsize().to_f64().unwrap();
+        let power_roots = Self::build_power_roots(&n_varsize, &factor_base, &roots, block_size);
+
+        let two_power_log = if factor_base.first() == Some(&2) {
+            let n_mod8 = n_varsize.clone().rem(8usize).to_u64().unwrap() as usize;
+            (n_mod8 == 1).then_some(2f64.ln())
+        } else {
+            None
+        };
+
+        let n_f = n_varsize.to_f64().unwrap();
 
         let treshold = (block_size as f64).ln() + n_f.ln() * 0.5
             - Self::chose_t(n_f.log10()) * (*factor_base.last().unwrap() as f64).ln();
@@ -343,12 +409,67 @@ impl<NT: NumberOps> LogSieve<NT> {
             n,
             factor_base,
             roots,
+            power_roots,
+            two_power_log,
             block_size,
             next_block: n.sqrt().add_usize(1),
             log_treshold: treshold,
         }
     }
 
+    /// Hensel-lifts each odd factor-base prime's root to every power `p^k <= block_size`, so the
+    /// sieve can add an extra `ln(p)` at the positions divisible by those higher powers too.
+    fn build_power_roots(
+        n_varsize: &num_bigint::BigUint,
+        factor_base: &[usize],
+        roots: &[Option<(usize, usize)>],
+        block_size: usize,
+    ) -> Vec<PrimePowerRoot> {
+        use crate::numbers::hensel_lift;
+
+        let mut power_roots = vec![];
+
+        for (&prime, &root_pair) in factor_base.iter().zip(roots.iter()) {
+            if prime == 2 {
+                continue;
+            }
+
+            let Some((mut root, _)) = root_pair else {
+                continue;
+            };
+
+            let mut power = prime;
+
+            loop {
+                let Some(next_power) = power.checked_mul(prime) else {
+                    break;
+                };
+
+                if next_power > block_size {
+                    break;
+                }
+
+                let n_mod = n_varsize.clone().rem(next_power).to_u64().unwrap() as usize;
+
+                let Some(lifted) = hensel_lift(n_mod, prime, power, root) else {
+                    break;
+                };
+
+                power = next_power;
+                root = lifted;
+
+                power_roots.push(PrimePowerRoot {
+                    modulus: power,
+                    root1: root,
+                    root2: power - root,
+                    log_p: (prime as f64).ln(),
+                });
+            }
+        }
+
+        power_roots
+    }
+
     fn chose_t(number_size: f64) -> f64 {
         if number_size <= 30.0 {
             return 1.5;
@@ -432,8 +553,51 @@ impl<NT: NumberOps> LogSieve<NT> {
 
                 idx += 2;
             }
+
+            if let Some(extra_log) = self.two_power_log {
+                let mut idx: usize = 0;
+
+                if !NumberOps::is_odd(&start) {
+                    idx += 1;
+                }
+
+                while idx < logs.len() {
+                    logs[idx] += extra_log;
+                    idx += 2;
+                }
+            }
+        }
+
+        for power_root in &self.power_roots {
+            for root in [power_root.root1, power_root.root2] {
+                let long_root = NT::convert_usize(root);
+                let long_modulus = NT::convert_usize(power_root.modulus);
+
+                let mut closest_element = (start.wrapping_sub(&long_root))
+                    .wrapping_div(&long_modulus)
+                    .wrapping_mul(&long_modulus)
+                    .wrapping_add(&long_root);
+
+                if closest_element < start {
+                    closest_element = closest_element.wrapping_add(&long_modulus);
+                }
+
+                let mut idx = closest_element.wrapping_sub(&start).to_usize();
+
+                while idx < logs.len() {
+                    logs[idx] += power_root.log_p;
+                    idx += power_root.modulus;
+                }
+            }
         }
 
+        let sub_segment_size = usize::min(LOGSIEVE_SUBSEGMENT_SIZE, logs.len()).max(1);
+        let num_segments = logs.len().div_ceil(sub_segment_size);
+
+        // per-segment bucket of (offset_within_segment, log_p, prime) hits still waiting to be
+        // applied; a large prime's next landing is only computed once its current hit is drained
+        let mut buckets: Vec<Vec<(usize, f64, usize)>> = vec![vec![]; num_segments];
+
         for (i, &prime) in self.factor_base.iter().enumerate() {
             //find start of sequence by trying different items
 
@@ -441,10 +605,10 @@ impl<NT: NumberOps> LogSieve<NT> {
                 continue;
             };
 
+            let root_log_value = (prime as f64).ln();
+
             for root in [s1, s2] {
                 //find closest value
-                let mut idx = 0;
-
                 let long_root = NT::convert_usize(root);
                 let long_prime = NT::convert_usize(prime);
 
@@ -457,22 +621,39 @@ impl<NT: NumberOps> LogSieve<NT> {
                     closest_element = closest_element.wrapping_add(&long_prime);
                 }
 
-                idx = closest_element.wrapping_sub(&start).to_usize();
+                let idx = closest_element.wrapping_sub(&start).to_usize();
 
                 debug_assert!({
                     let (_, r) = (start.wrapping_add(&NT::convert_usize(idx))).divmod(prime);
                     r == NT::convert_usize(root)
                 });
 
-                let root_log_value = (prime as f64).ln();
-
                 #[cfg(feature = "verbose")]
                 println!("prime is {prime}, root is {root}, idx is {idx}");
 
-                while idx < logs.len() {
-                    logs[idx] += root_log_value;
+                if prime <= sub_segment_size {
+                    // small strides stay L2-resident over their own sweep; sieve them directly
+                    let mut idx = idx;
+                    while idx < logs.len() {
+                        logs[idx] += root_log_value;
+                        idx += prime;
+                    }
+                } else if idx < logs.len() {
+                    buckets[idx / sub_segment_size].push((idx % sub_segment_size, root_log_value, prime));
+                }
+            }
+        }
 
-                    idx += prime;
+        for segment in 0..num_segments {
+            let segment_start = segment * sub_segment_size;
+
+            let hits = std::mem::take(&mut buckets[segment]);
+            for (offset, log_p, prime) in hits {
+                logs[segment_start + offset] += log_p;
+
+                let next_idx = segment_start + offset + prime;
+                if next_idx < logs.len() {
+                    buckets[next_idx / sub_segment_size].push((next_idx % sub_segment_size, log_p, prime));
                 }
             }
         }
@@ -493,7 +674,7 @@ impl<NT: NumberOps> LogSieve<NT> {
                 return None;
             };
 
-                Some(SmoothNumber { number, divisors })
+                Some(SmoothNumber { number, divisors, large_prime: None })
             })
             .collect_vec()
     }
@@ -634,10 +815,382 @@ impl<NT: NumberOps> LogSieve<NT> {
     }
 }
 
+/// the `(a, b, c)` of one self-initializing polynomial `g(x) = a*(a*x^2 + 2*b*x + c)`, chosen so
+/// that `(a*x + b)^2 === a*(a*x^2 + 2*b*x + c) (mod n)`. `a` is a product of factor-base primes
+/// not otherwise used for sieving this polynomial, and `a_factors` records that factorization so
+/// relations can carry it without re-deriving it.
+struct MpqsPolynomial {
+    a: BigUint,
+    a_factors: Vec<(usize, usize)>,
+    b: BigUint,
+    c: BigInt,
+}
+
+/// reduces `lhs - rhs` into `[0, modulus)`, for `BigUint`s where the subtraction may go negative.
+fn sub_mod(lhs: &BigUint, rhs: &BigUint, modulus: &BigUint) -> BigUint {
+    let modulus = BigInt::from(modulus.clone());
+    let diff = BigInt::from(lhs.clone()) - BigInt::from(rhs.clone());
+    (((diff % &modulus) + &modulus) % &modulus)
+        .to_biguint()
+        .unwrap()
+}
+
+/// self-initializing multiple-polynomial sieve: instead of a single, ever-growing `x^2 - n`,
+/// sieves many polynomials `g(x) = (a*x + b)^2 - n` over a fixed small interval `[-m, m]`, so
+/// residues stay small and smoothness probability doesn't collapse for large `n`.
+pub struct MpqsSieve<NT: NumberOps> {
+    n: NT,
+    /// factor-base primes sieved against each polynomial (`factor_base` minus `a_primes`)
+    sieve_primes: Vec<usize>,
+    /// `tonelli_shanks(n mod prime, prime)` for each prime in `sieve_primes` (`None` for `2`,
+    /// which is excluded from root-based sieving the same way `BlockSieve`/`LogSieve` special-case
+    /// it)
+    sieve_roots: Vec<Option<usize>>,
+    /// `a^{-1} mod prime` for each prime in `sieve_primes`, refreshed whenever `a_primes` changes
+    a_inverses: Vec<Option<usize>>,
+    /// current per-prime sieve roots `x === a^{-1} * (+-t_p - b) (mod p)`, refreshed in O(k) every
+    /// time `b` changes instead of falling back to full trial division
+    roots: Vec<Option<(usize, usize)>>,
+    /// half-width of the sieve interval; each polynomial is swept over `x in [-m, m)`
+    m: usize,
+    /// factor-base primes chosen to build `a` for the current polynomial batch
+    a_primes: Vec<usize>,
+    /// per-`a_primes[i]` CRT basis term (`B_i === +-a_roots[i] (mod a_primes[i])`, `=== 0` mod the
+    /// others) used to move `b` to the next sign combination in O(1) instead of re-solving the CRT
+    b_terms: Vec<BigUint>,
+    /// index into the Gray-code sequence of the `2^(a_primes.len() - 1)` sign combinations
+    poly_step: usize,
+    polynomial: MpqsPolynomial,
+}
+
+impl<NT: NumberOps> MpqsSieve<NT> {
+    pub fn new(n: NT, factor_base: Vec<usize>, m: usize) -> Self {
+        let n_big = n.to_varsize();
+
+        let a_primes = Self::choose_a_primes(&n_big, &factor_base, m);
+        assert!(
+            a_primes.len() >= 2,
+            "factor base is too small to build an `a` coefficient for MPQS"
+        );
+
+        let sieve_primes = factor_base
+            .iter()
+            .cloned()
+            .filter(|prime| !a_primes.contains(prime))
+            .collect_vec();
+
+        let sieve_roots = sieve_primes
+            .iter()
+            .map(|&prime| {
+                if prime == 2 {
+                    return None;
+                }
+                let residue = n_big.clone().rem(prime).to_u64().unwrap() as usize;
+                tonelli_shanks(residue, prime)
+            })
+            .collect_vec();
+
+        let mut sieve = MpqsSieve {
+            n,
+            sieve_primes,
+            sieve_roots,
+            a_inverses: vec![],
+            roots: vec![],
+            m,
+            a_primes,
+            b_terms: vec![],
+            poly_step: 0,
+            polynomial: MpqsPolynomial {
+                a: BigUint::from(0u32),
+                a_factors: vec![],
+                b: BigUint::from(0u32),
+                c: BigInt::from(0),
+            },
+        };
+
+        sieve.rebuild_a();
+        sieve
+    }
+
+    /// picks factor-base primes (largest first, skipping 2) whose product is close to the
+    /// classic SIQS target `a ~ sqrt(2n) / m`.
+    fn choose_a_primes(n_big: &BigUint, factor_base: &[usize], m: usize) -> Vec<usize> {
+        let target = (n_big.to_f64().unwrap() * 2f64).sqrt() / m as f64;
+
+        let mut primes = vec![];
+        let mut product = 1f64;
+
+        for &prime in factor_base.iter().rev() {
+            if prime == 2 || product >= target {
+                continue;
+            }
+            primes.push(prime);
+            product *= prime as f64;
+        }
+
+        primes
+    }
+
+    /// (re)builds everything that only depends on the chosen `a_primes`: `a` itself, the CRT
+    /// basis terms `b_terms` used to move between sign combinations, and `a^{-1} mod p` for every
+    /// sieving prime. Then loads the all-positive-signs polynomial (`poly_step == 0`).
+    fn rebuild_a(&mut self) {
+        let a: BigUint = self.a_primes.iter().map(|&p| BigUint::from(p)).product();
+
+        let n_big = self.n.to_varsize();
+        let a_roots = self
+            .a_primes
+            .iter()
+            .map(|&prime| {
+                let residue = n_big.clone().rem(prime).to_u64().unwrap() as usize;
+                tonelli_shanks(residue, prime)
+                    .expect("a-primes are taken from the factor base, so n is a QR mod each one")
+            })
+            .collect_vec();
+
+        self.b_terms = self
+            .a_primes
+            .iter()
+            .zip(a_roots.iter())
+            .map(|(&prime, &root)| {
+                // B_i === root (mod prime), B_i === 0 (mod every other a-prime)
+                let cofactor = a.clone() / BigUint::from(prime);
+                let cofactor_mod_p = cofactor.clone().rem(prime).to_u64().unwrap() as usize;
+                let cofactor_inv = mod_inverse(cofactor_mod_p, prime)
+                    .expect("a-primes are pairwise distinct, hence coprime");
+                cofactor * BigUint::from(mulmod(root, cofactor_inv, prime))
+            })
+            .collect_vec();
+
+        self.a_inverses = self
+            .sieve_primes
+            .iter()
+            .map(|&prime| {
+                let a_mod_p = a.clone().rem(prime).to_u64().unwrap() as usize;
+                mod_inverse(a_mod_p, prime)
+            })
+            .collect_vec();
+
+        self.polynomial.a_factors = self.a_primes.iter().map(|&p| (p, 1usize)).collect_vec();
+        self.polynomial.a = a;
+        self.poly_step = 0;
+
+        let b: BigUint = self
+            .b_terms
+            .iter()
+            .fold(BigUint::from(0u32), |acc, term| {
+                (acc + term).rem(&self.polynomial.a)
+            });
+        self.set_b(b);
+    }
+
+    /// installs `b` (and the `c`, per-prime sieve roots that follow from it).
+    fn set_b(&mut self, b: BigUint) {
+        let n_big = self.n.to_varsize();
+        let b_signed = BigInt::from(b.clone());
+
+        // by construction b^2 === n (mod a), so (b^2 - n) is exactly divisible by a
+        let a_int = BigInt::from(self.polynomial.a.clone());
+        let c = (&b_signed * &b_signed - BigInt::from(n_big)) / a_int;
+
+        self.polynomial.b = b;
+        self.polynomial.c = c;
+
+        self.roots = self
+            .sieve_primes
+            .iter()
+            .zip(self.sieve_roots.iter())
+            .zip(self.a_inverses.iter())
+            .map(|((&prime, &t_p), &a_inv)| {
+                let t_p = t_p?;
+                let a_inv = a_inv?;
+                let b_mod_p = self.polynomial.b.clone().rem(prime).to_u64().unwrap() as usize;
+
+                let root1 = mulmod(a_inv, (t_p + prime - b_mod_p) % prime, prime);
+                let root2 = mulmod(a_inv, (prime - t_p + prime - b_mod_p) % prime, prime);
+                Some((root1, root2))
+            })
+            .collect_vec();
+    }
+
+    /// switches to the next sign combination in Gray-code order, updating `b` (and the per-prime
+    /// sieve roots it determines) in O(k) instead of re-solving the CRT from scratch. Once every
+    /// combination for the current `a_primes` has been tried, wraps back around to the base
+    /// polynomial (picking a fresh `a` is left to the caller, which can build a new `MpqsSieve`
+    /// with a different `m`).
+    pub fn next_polynomial(&mut self) {
+        let total_combos = 1usize << (self.a_primes.len() - 1);
+        self.poly_step = (self.poly_step + 1) % total_combos;
+
+        if self.poly_step == 0 {
+            let b: BigUint = self
+                .b_terms
+                .iter()
+                .fold(BigUint::from(0u32), |acc, term| {
+                    (acc + term).rem(&self.polynomial.a)
+                });
+            self.set_b(b);
+            return;
+        }
+
+        // standard Gray-code self-initialization (Contini): step `v` differs from `v - 1` by
+        // exactly one sign, on the a-prime indexed by `v`'s lowest set bit
+        let i = self.poly_step.trailing_zeros() as usize;
+        let flip_to_negative = (self.poly_step >> (i + 1)) & 1 == 0;
+
+        let delta = &self.b_terms[i] * BigUint::from(2u32);
+        let b = if flip_to_negative {
+            sub_mod(&self.polynomial.b, &delta, &self.polynomial.a)
+        } else {
+            (&self.polynomial.b + &delta).rem(&self.polynomial.a)
+        };
+
+        self.set_b(b);
+    }
+
+    pub fn run(&mut self, numbers_to_find: usize) -> SmoothiesVec<NT> {
+        let mut result = vec![];
+
+        while result.len() < numbers_to_find {
+            result.append(&mut self.search_polynomial());
+            self.next_polynomial();
+        }
+
+        result
+    }
+
+    /// sieves the current polynomial over `x in [-m, m)` using the per-prime roots in `self.roots`
+    /// (instead of trial-dividing every `x`), then confirms each candidate that crosses the log
+    /// threshold with an exact trial division.
+    fn search_polynomial(&self) -> SmoothiesVec<NT> {
+        let width = 2 * self.m;
+        let mut logs = vec![0f64; width];
+
+        for (&prime, root_pair) in self.sieve_primes.iter().zip(self.roots.iter()) {
+            let Some((r1, r2)) = root_pair else {
+                continue;
+            };
+
+            let log_p = (prime as f64).ln();
+
+            for &root in [r1, r2].iter() {
+                // `x = idx - m`, so the first index `>= 0` with `idx === root (mod prime)` is the
+                // smallest `idx` congruent to `(root - (-m)) mod prime`, i.e. `(root + m) mod prime`
+                let mut idx = (root + self.m) % prime;
+
+                while idx < width {
+                    logs[idx] += log_p;
+                    idx += prime;
+                }
+            }
+        }
+
+        let n_f = self.n.to_varsize().to_f64().unwrap();
+        let largest_prime = self.sieve_primes.last().copied().unwrap_or(2) as f64;
+        let threshold = 0.5 * n_f.ln() + (self.m as f64).ln().max(1.0) - largest_prime.ln();
+
+        let a_int = BigInt::from(self.polynomial.a.clone());
+        let b_int = BigInt::from(self.polynomial.b.clone());
+
+        logs.into_iter()
+            .enumerate()
+            .filter(|&(_, ln)| ln >= threshold)
+            .filter_map(|(idx, _)| {
+                let x = idx as i64 - self.m as i64;
+                let x_big = BigInt::from(x);
+
+                // Q(x) = a*x^2 + 2*b*x + c, so that a*Q(x) = (a*x + b)^2 - n exactly
+                let value = &a_int * &x_big * &x_big + BigInt::from(2) * &b_int * &x_big
+                    + &self.polynomial.c;
+
+                // the sieve doesn't track the sign of Q(x) (it can be negative near the vertex);
+                // a relation built from an odd number of negative terms will simply fail the
+                // final gcd test downstream rather than being caught here
+                let magnitude = value.magnitude();
+
+                let mut divisors = trial_divide_biguint(magnitude, &self.sieve_primes)?;
+                divisors.extend(self.polynomial.a_factors.iter().cloned());
+                divisors.sort_unstable();
+
+                let root_magnitude = (&a_int * &x_big + &b_int).magnitude().clone();
+                let number = NT::from_varsize(&root_magnitude);
+
+                Some(SmoothNumber { number, divisors, large_prime: None })
+            })
+            .collect_vec()
+    }
+}
+
+/// multiplies two partial relations sharing the same `large_prime` into one full relation: the
+/// large prime appears squared in the product and is divided back out via its modular inverse
+/// mod `n`, so the result is an ordinary smooth relation ready for the solver. Returns `None` in
+/// the (rare, lucky) case where the large prime shares a factor with `n` itself - that's a direct
+/// hit on `n`, not something this pass is responsible for reporting.
+fn combine_two_partials<NT: NumberOps>(
+    n: &NT,
+    first: &SmoothNumber<NT>,
+    second: &SmoothNumber<NT>,
+    large_prime: usize,
+) -> Option<SmoothNumber<NT>> {
+    let n_big = n.to_varsize();
+    let inverse = mod_inverse_biguint(&BigUint::from(large_prime), &n_big)?;
+
+    let combined = (first.number.to_varsize() * second.number.to_varsize() * inverse).rem(&n_big);
+
+    let mut divisors = first.divisors.clone();
+    for &(prime, exponent) in &second.divisors {
+        match divisors.iter_mut().find(|(p, _)| *p == prime) {
+            Some((_, existing)) => *existing += exponent,
+            None => divisors.push((prime, exponent)),
+        }
+    }
+    divisors.sort_unstable();
+
+    Some(SmoothNumber {
+        number: NT::from_varsize(&combined),
+        divisors,
+        large_prime: None,
+    })
+}
+
+/// combines partial relations left over from sieving using [`combine_large_primes`]'s
+/// union-find/checklist chaining: relations sharing the same large prime are glued together in
+/// the order they were produced, so a large prime seen more than twice still gets folded into a
+/// chain of full relations instead of only draining matched pairs two at a time. A large prime
+/// left with an unpaired partial relation at the end is dropped, same as before.
+pub fn combine_partial_relations<NT: NumberOps>(
+    n: &NT,
+    relations: SmoothiesVec<NT>,
+) -> SmoothiesVec<NT> {
+    let partials = relations
+        .iter()
+        .map(|relation| PartialRelation {
+            divisors: relation.divisors.clone(),
+            large_prime: relation.large_prime,
+        })
+        .collect_vec();
+
+    let (_rows, provenance) = combine_large_primes(&partials);
+
+    provenance
+        .into_iter()
+        .filter_map(|group| match group.as_slice() {
+            &[single] => Some(relations[single].clone()),
+            &[first, second] => {
+                let large_prime = relations[first]
+                    .large_prime
+                    .expect("combine_large_primes only pairs relations carrying a large prime");
+                combine_two_partials(n, &relations[first], &relations[second], large_prime)
+            }
+            _ => unreachable!("combine_large_primes only ever emits groups of 1 or 2"),
+        })
+        .collect_vec()
+}
+
 #[cfg(test)]
 mod tests {
-
-    use num_bigint::BigInt;
+    use super::*;
+    use crypto_bigint::U64;
 
     #[test]
     fn negative_power_building() {
@@ -647,4 +1200,152 @@ mod tests {
             BigInt::from(3)
         );
     }
+
+    #[test]
+    fn build_power_roots_matches_hand_lifted_root() {
+        use crate::numbers::hensel_lift;
+
+        // 3^2 === 9 (mod 13); lift that root to mod 169, same example as
+        // `hensel_lift_produces_valid_root` in `numbers.rs`.
+        let root1 = tonelli_shanks(9, 13).unwrap();
+        let root2 = 13 - root1;
+
+        let n_varsize = BigUint::from(9u32);
+        let power_roots =
+            LogSieve::<U64>::build_power_roots(&n_varsize, &[13], &[Some((root1, root2))], 200);
+
+        assert_eq!(power_roots.len(), 1);
+        let lifted = &power_roots[0];
+        let expected = hensel_lift(9, 13, 13, root1).unwrap();
+
+        assert_eq!(lifted.modulus, 169);
+        assert_eq!(lifted.root1, expected);
+        assert_eq!(lifted.root2, 169 - expected);
+        assert_eq!((lifted.root1 * lifted.root1) % 169, 9);
+    }
+
+    #[test]
+    fn mpqs_polynomial_satisfies_core_identity() {
+        use crate::numbers::build_factor_base;
+
+        let n = U64::convert_usize(8051); // 83 * 97
+        let factor_base = build_factor_base(&n, 50);
+        // a small `m` pushes the SIQS target `sqrt(2n)/m` above any single factor-base prime, so
+        // `choose_a_primes` is forced to combine more than one prime into `a`
+        let sieve = MpqsSieve::new(n, factor_base, 1);
+
+        let a = sieve.polynomial.a.clone();
+        let b = sieve.polynomial.b.clone();
+        let n_big = n.to_varsize();
+
+        // self-initialization invariant: `b` is built from the CRT basis terms so that
+        // `b^2 === n (mod a)`, which is what lets `set_b` divide `(b^2 - n)` by `a` exactly.
+        assert_eq!(b.clone().modpow(&BigUint::from(2u32), &a), n_big.clone().rem(&a));
+
+        let a_int = BigInt::from(a);
+        let b_int = BigInt::from(b);
+        let n_int = BigInt::from(n_big);
+
+        // core MPQS identity: `a * Q(x) == (a*x + b)^2 - n` for every `x`, smooth or not
+        // (see the matching formula in `search_polynomial`).
+        for x in [-17i64, -1, 0, 5, 42] {
+            let x_big = BigInt::from(x);
+            let q = &a_int * &x_big * &x_big + BigInt::from(2) * &b_int * &x_big
+                + &sieve.polynomial.c;
+            let lhs = (&a_int * &x_big + &b_int) * (&a_int * &x_big + &b_int) - &n_int;
+            assert_eq!(lhs, &a_int * &q);
+        }
+    }
+
+    #[test]
+    fn mpqs_sieve_reuses_self_init_state_across_repeated_run_calls() {
+        use crate::numbers::build_factor_base;
+
+        let n = U64::convert_usize(8051); // 83 * 97
+        let factor_base = build_factor_base(&n, 50);
+        let mut sieve = MpqsSieve::new(n, factor_base, 1);
+
+        let a_before = sieve.polynomial.a.clone();
+        let step_before = sieve.poly_step;
+
+        // `run_factor`'s retry loop keeps driving the same `MpqsSieve` instance across several
+        // `run` calls instead of rebuilding one from scratch each time; that only pays off if
+        // self-init state (in particular `a`, which is expensive to rebuild) survives across them.
+        sieve.run(1);
+
+        assert_eq!(
+            sieve.polynomial.a, a_before,
+            "a should not be rebuilt just from calling run() again"
+        );
+        assert_ne!(
+            sieve.poly_step, step_before,
+            "poly_step should have advanced via next_polynomial across the run() call"
+        );
+    }
+
+    #[test]
+    fn combine_two_partials_cancels_shared_large_prime() {
+        let n = U64::convert_usize(1009);
+        let large_prime = 13usize;
+
+        let first = SmoothNumber {
+            number: U64::convert_usize(5),
+            divisors: vec![(2, 1)],
+            large_prime: Some(large_prime),
+        };
+        let second = SmoothNumber {
+            number: U64::convert_usize(11),
+            divisors: vec![(3, 1)],
+            large_prime: Some(large_prime),
+        };
+
+        let combined = combine_two_partials(&n, &first, &second, large_prime)
+            .expect("13 does not divide 1009");
+
+        let inverse = mod_inverse_biguint(&BigUint::from(large_prime), &n.to_varsize()).unwrap();
+        let expected =
+            (BigUint::from(5u32) * BigUint::from(11u32) * inverse).rem(&n.to_varsize());
+
+        assert_eq!(combined.number.to_varsize(), expected);
+        assert_eq!(combined.divisors, vec![(2, 1), (3, 1)]);
+        assert_eq!(combined.large_prime, None);
+    }
+
+    #[test]
+    fn combine_partial_relations_pairs_matching_large_primes_and_drops_odd_one_out() {
+        let n = U64::convert_usize(1009);
+
+        let full = SmoothNumber {
+            number: U64::convert_usize(4),
+            divisors: vec![(2, 2)],
+            large_prime: None,
+        };
+        let partial_a1 = SmoothNumber {
+            number: U64::convert_usize(5),
+            divisors: vec![(2, 1)],
+            large_prime: Some(13),
+        };
+        let partial_a2 = SmoothNumber {
+            number: U64::convert_usize(11),
+            divisors: vec![(3, 1)],
+            large_prime: Some(13),
+        };
+        let partial_b = SmoothNumber {
+            number: U64::convert_usize(7),
+            divisors: vec![(5, 1)],
+            large_prime: Some(17),
+        };
+
+        let relations = vec![full.clone(), partial_a1, partial_a2, partial_b];
+        let combined = combine_partial_relations(&n, relations);
+
+        // the pre-existing full relation passes through untouched, the pair sharing large_prime
+        // 13 combines into one more full relation, and the unpaired large_prime 17 partial is
+        // dropped
+        assert_eq!(combined.len(), 2);
+        assert!(combined.iter().all(|r| r.large_prime.is_none()));
+        assert!(combined
+            .iter()
+            .any(|r| r.number.to_varsize() == full.number.to_varsize()));
+    }
 }
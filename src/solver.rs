@@ -1,10 +1,30 @@
 use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
+    io::{self, BufRead, Write},
     ops::AddAssign,
 };
 
 use itertools::Itertools;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Once a row's density (set bits / column count) crosses this fraction, elimination
+/// fill-in has made the sorted-merge representation slower than a packed XOR, so the
+/// row is promoted to [`PackedRow`].
+const PACKED_DENSITY_THRESHOLD: f64 = 0.2;
+
+/// Either row representation used by [`CongruenceSystem`]. `diagonalize`/`fast_pivot`
+/// only ever go through [`MatrixRow`], which dispatches to this trait, so they stay
+/// oblivious to which backing store a given row currently uses.
+pub trait RowRepr {
+    fn add_assign_row(&mut self, other: &Self);
+    fn contains(&self, item: usize) -> bool;
+    fn is_zero(&self) -> bool;
+    fn least_term(&self) -> Option<usize>;
+    fn weight(&self) -> usize;
+    fn to_sorted_items(&self) -> Vec<usize>;
+}
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct SparseRow {
@@ -109,14 +129,360 @@ impl Ord for SparseRow {
     }
 }
 
+impl RowRepr for SparseRow {
+    fn add_assign_row(&mut self, other: &Self) {
+        self.add_inpace(other)
+    }
+
+    fn contains(&self, item: usize) -> bool {
+        SparseRow::contains(self, item)
+    }
+
+    fn is_zero(&self) -> bool {
+        SparseRow::is_zero(self)
+    }
+
+    fn least_term(&self) -> Option<usize> {
+        SparseRow::least_term(self)
+    }
+
+    fn weight(&self) -> usize {
+        self.items.len()
+    }
+
+    fn to_sorted_items(&self) -> Vec<usize> {
+        self.items.clone()
+    }
+}
+
+/// Dense bitmap row, one bit per column in the range `0..width`. Used once a row's
+/// fill-in makes the sorted-merge `SparseRow` representation slower than a
+/// word-parallel XOR.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PackedRow {
+    width: usize,
+    words: Vec<u64>,
+}
+
+impl PackedRow {
+    fn zero(width: usize) -> Self {
+        PackedRow {
+            width,
+            words: vec![0u64; width.div_ceil(64).max(1)],
+        }
+    }
+
+    fn from_items(items: &[usize], width: usize) -> Self {
+        let mut row = Self::zero(width);
+        for &item in items {
+            row.set(item);
+        }
+        row
+    }
+
+    fn set(&mut self, item: usize) {
+        self.words[item / 64] |= 1 << (item % 64);
+    }
+
+    fn toggle(&mut self, item: usize) {
+        self.words[item / 64] ^= 1 << (item % 64);
+    }
+}
+
+impl RowRepr for PackedRow {
+    fn add_assign_row(&mut self, other: &Self) {
+        debug_assert_eq!(self.width, other.width);
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a ^= b;
+        }
+    }
+
+    fn contains(&self, item: usize) -> bool {
+        (self.words[item / 64] >> (item % 64)) & 1 == 1
+    }
+
+    fn is_zero(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    fn least_term(&self) -> Option<usize> {
+        self.words.iter().enumerate().find_map(|(i, &word)| {
+            if word == 0 {
+                None
+            } else {
+                Some(i * 64 + word.trailing_zeros() as usize)
+            }
+        })
+    }
+
+    fn weight(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    fn to_sorted_items(&self) -> Vec<usize> {
+        let mut out = vec![];
+        for (i, &word) in self.words.iter().enumerate() {
+            let mut bits = word;
+            while bits != 0 {
+                let trailing = bits.trailing_zeros() as usize;
+                out.push(i * 64 + trailing);
+                bits &= bits - 1;
+            }
+        }
+        out
+    }
+}
+
+/// A row of the congruence matrix. Starts out [`SparseRow`] (matrices built from
+/// smooth-number relations are naturally sparse) and is transparently promoted to
+/// [`PackedRow`] by [`MatrixRow::add_assign_row`] once its density crosses
+/// [`PACKED_DENSITY_THRESHOLD`].
+#[derive(Clone)]
+pub enum MatrixRow {
+    Sparse(SparseRow),
+    Packed(PackedRow),
+}
+
+impl MatrixRow {
+    fn should_promote(weight: usize, width: usize) -> bool {
+        width > 0 && weight as f64 / width as f64 > PACKED_DENSITY_THRESHOLD
+    }
+
+    /// XOR `other` into `self`, promoting to `PackedRow` if the result got dense.
+    /// `width` is the number of columns in the system this row belongs to.
+    fn add_assign_row(&mut self, other: &MatrixRow, width: usize) {
+        match (&mut *self, other) {
+            (MatrixRow::Sparse(a), MatrixRow::Sparse(b)) => {
+                a.add_assign_row(b);
+                if Self::should_promote(a.weight(), width) {
+                    *self = MatrixRow::Packed(PackedRow::from_items(&a.items, width));
+                }
+            }
+            (MatrixRow::Sparse(a), MatrixRow::Packed(b)) => {
+                let mut packed = PackedRow::from_items(&a.items, width);
+                packed.add_assign_row(b);
+                *self = MatrixRow::Packed(packed);
+            }
+            (MatrixRow::Packed(a), MatrixRow::Sparse(b)) => {
+                for &item in &b.items {
+                    a.toggle(item);
+                }
+            }
+            (MatrixRow::Packed(a), MatrixRow::Packed(b)) => a.add_assign_row(b),
+        }
+    }
+
+    fn contains(&self, item: usize) -> bool {
+        match self {
+            MatrixRow::Sparse(row) => row.contains(item),
+            MatrixRow::Packed(row) => row.contains(item),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            MatrixRow::Sparse(row) => row.is_zero(),
+            MatrixRow::Packed(row) => row.is_zero(),
+        }
+    }
+
+    fn least_term(&self) -> Option<usize> {
+        match self {
+            MatrixRow::Sparse(row) => row.least_term(),
+            MatrixRow::Packed(row) => row.least_term(),
+        }
+    }
+
+    fn weight(&self) -> usize {
+        match self {
+            MatrixRow::Sparse(row) => row.weight(),
+            MatrixRow::Packed(row) => row.weight(),
+        }
+    }
+
+    fn to_sorted_items(&self) -> Vec<usize> {
+        match self {
+            MatrixRow::Sparse(row) => row.to_sorted_items(),
+            MatrixRow::Packed(row) => row.to_sorted_items(),
+        }
+    }
+}
+
+impl From<SparseRow> for MatrixRow {
+    fn from(row: SparseRow) -> Self {
+        MatrixRow::Sparse(row)
+    }
+}
+
+impl PartialEq for MatrixRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_sorted_items() == other.to_sorted_items()
+    }
+}
+
+impl Eq for MatrixRow {}
+
+impl PartialOrd for MatrixRow {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MatrixRow {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.least_term(), other.least_term()) {
+            (None, None) => Ordering::Equal,
+            (Some(_), None) => Ordering::Less, //we want zeros to go last
+            (None, Some(_)) => Ordering::Greater,
+            _ => self.to_sorted_items().cmp(&other.to_sorted_items()),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
 pub struct CongruenceSystem {
-    rows: Vec<SparseRow>,
+    rows: Vec<MatrixRow>,
     row_labels: Vec<usize>,
     x_labels: Vec<usize>,
+    /// one past the largest `x_label` (labels are used directly as bit positions); the bit
+    /// width a row is given when promoted to `PackedRow`
+    width: usize,
+}
+
+fn column_width(x_labels: &[usize]) -> usize {
+    x_labels.iter().max().map_or(0, |&max| max + 1)
 }
 
 pub type SparseCountMap = Vec<Vec<(usize, usize)>>;
 
+/// Number of candidate null-space vectors a block Lanczos block carries in parallel, one bit
+/// per `u64` word.
+const LANCZOS_BLOCK: usize = 64;
+
+/// A dense `LANCZOS_BLOCK x LANCZOS_BLOCK` matrix over GF(2), row `i` packed into `rows[i]`.
+type Block = [u64; LANCZOS_BLOCK];
+
+fn block_zero() -> Block {
+    [0u64; LANCZOS_BLOCK]
+}
+
+fn block_identity() -> Block {
+    let mut identity = block_zero();
+    for (i, row) in identity.iter_mut().enumerate() {
+        *row = 1 << i;
+    }
+    identity
+}
+
+fn block_is_zero(matrix: &Block) -> bool {
+    matrix.iter().all(|&row| row == 0)
+}
+
+/// `a * b` over GF(2): row `i` of the product is the XOR of the rows of `b` selected by the
+/// set bits of row `i` of `a`.
+fn block_mul(a: &Block, b: &Block) -> Block {
+    let mut result = block_zero();
+    for i in 0..LANCZOS_BLOCK {
+        let mut bits = a[i];
+        let mut acc = 0u64;
+        while bits != 0 {
+            let j = bits.trailing_zeros() as usize;
+            acc ^= b[j];
+            bits &= bits - 1;
+        }
+        result[i] = acc;
+    }
+    result
+}
+
+/// `u^T * v` for two block vectors of equal length: row `i` of the result is the XOR of the
+/// `v` columns whose corresponding `u` entry has bit `i` set.
+fn block_gram(u: &[u64], v: &[u64]) -> Block {
+    let mut result = block_zero();
+    for bit in 0..LANCZOS_BLOCK {
+        let mut acc = 0u64;
+        for (&uk, &vk) in u.iter().zip(v.iter()) {
+            if (uk >> bit) & 1 == 1 {
+                acc ^= vk;
+            }
+        }
+        result[bit] = acc;
+    }
+    result
+}
+
+/// Gauss-Jordan elimination restricted to the largest invertible principal minor it can find:
+/// a column that never turns up a free pivot row is left out of the returned mask and zeroed
+/// in the inverse, which is exactly the "selection matrix" `Sᵢ` from Montgomery's recurrence.
+fn block_invert_with_mask(matrix: &Block) -> (Block, u64) {
+    let mut left = *matrix;
+    let mut right = block_identity();
+    let mut pivot_row_of = [usize::MAX; LANCZOS_BLOCK];
+    let mut used_rows = 0u64;
+    let mut mask = 0u64;
+
+    for col in 0..LANCZOS_BLOCK {
+        let Some(pivot_row) =
+            (0..LANCZOS_BLOCK).find(|&r| (used_rows >> r) & 1 == 0 && (left[r] >> col) & 1 == 1)
+        else {
+            continue;
+        };
+
+        used_rows |= 1 << pivot_row;
+        pivot_row_of[col] = pivot_row;
+        mask |= 1 << col;
+
+        for r in 0..LANCZOS_BLOCK {
+            if r != pivot_row && (left[r] >> col) & 1 == 1 {
+                left[r] ^= left[pivot_row];
+                right[r] ^= right[pivot_row];
+            }
+        }
+    }
+
+    let mut inverse = block_zero();
+    for (col, &row) in pivot_row_of.iter().enumerate() {
+        if row != usize::MAX {
+            inverse[col] = right[row];
+        }
+    }
+
+    (inverse, mask)
+}
+
+fn block_vector_apply_mask(vector: &[u64], mask: u64) -> Vec<u64> {
+    vector.iter().map(|&word| word & mask).collect_vec()
+}
+
+/// `vector * matrix`: entry `k` of the result is the XOR of the matrix rows selected by the
+/// set bits of `vector[k]`.
+fn block_vector_mul(vector: &[u64], matrix: &Block) -> Vec<u64> {
+    vector
+        .iter()
+        .map(|&word| {
+            let mut bits = word;
+            let mut acc = 0u64;
+            while bits != 0 {
+                let i = bits.trailing_zeros() as usize;
+                acc ^= matrix[i];
+                bits &= bits - 1;
+            }
+            acc
+        })
+        .collect_vec()
+}
+
+fn block_vector_xor(a: &[u64], b: &[u64]) -> Vec<u64> {
+    a.iter().zip(b.iter()).map(|(&x, &y)| x ^ y).collect_vec()
+}
+
+fn random_block_vector(width: usize, rng: &mut impl Rng) -> Vec<u64> {
+    (0..width).map(|_| rng.gen::<u64>()).collect_vec()
+}
+
 impl CongruenceSystem {
     pub fn new(rows: &SparseCountMap, row_labels: Vec<usize>) -> Self {
         assert_eq!(rows.len(), row_labels.len());
@@ -134,10 +500,13 @@ impl CongruenceSystem {
 
         labels.sort_unstable();
 
+        let width = column_width(&labels);
+
         Self {
-            rows,
+            rows: rows.into_iter().map(MatrixRow::from).collect_vec(),
             x_labels: labels,
             row_labels,
+            width,
         }
     }
 
@@ -153,14 +522,17 @@ impl CongruenceSystem {
             .map(|item| SparseRow::from(&item[..]))
             .collect_vec();
 
+        let width = column_width(&x_labels);
+
         Self {
-            rows,
+            rows: rows.into_iter().map(MatrixRow::from).collect_vec(),
             row_labels,
             x_labels,
+            width,
         }
     }
 
-    fn reorder_descending_slice(slice: &mut [SparseRow]) {
+    fn reorder_descending_slice(slice: &mut [MatrixRow]) {
         slice.sort();
     }
 
@@ -168,9 +540,15 @@ impl CongruenceSystem {
         Self::reorder_descending_slice(&mut self.rows)
     }
 
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
     pub fn diagonalize(&mut self) {
         self.reorder_descending();
 
+        let width = self.width;
+
         for row_number in 0..self.rows.len() {
             if self.rows[row_number].is_zero() {
                 break;
@@ -179,13 +557,13 @@ impl CongruenceSystem {
             let term = self.rows[row_number].least_term().unwrap();
 
             for affected_row in (row_number + 1)..self.rows.len() {
-                let Some(other_term) = self.rows[affected_row].least_term()  else {
+                let Some(other_term) = self.rows[affected_row].least_term() else {
                     continue;
                 };
                 if other_term == term {
                     //these two rows are always different
                     let (reference, affected) = self.rows.split_at_mut(affected_row);
-                    affected[0] += &reference[row_number];
+                    affected[0].add_assign_row(&reference[row_number], width);
                 }
             }
 
@@ -230,22 +608,183 @@ impl CongruenceSystem {
                     })
                     .collect_vec();
 
-                SparseRow::from(&items[..])
+                MatrixRow::from(SparseRow::from(&items[..]))
             })
             .collect_vec();
 
+        let width = column_width(&new_x);
+
         CongruenceSystem {
             rows: new_rows,
             row_labels: new_row_labels,
             x_labels: new_x,
+            width,
         }
     }
 
+    /// Structured Gaussian elimination pre-pass. Repeatedly strips columns that can
+    /// never contribute a useful choice for `diagonalize`/`fast_pivot`:
+    /// - weight-0 columns are simply dropped,
+    /// - a weight-1 column's single row can never appear in a null-space combination
+    ///   and is deleted outright,
+    /// - a weight-2 column is eliminated by XORing its two rows together and
+    ///   discarding the now-redundant donor row.
+    ///
+    /// Stops once no weight-0/1/2 column remains or `columns - rows` reaches
+    /// `min_excess`. Returns the reduced system together with, for each surviving
+    /// row (in order), the original `row_labels` that were merged into it.
+    pub fn filter(&self, min_excess: usize) -> (CongruenceSystem, Vec<Vec<usize>>) {
+        let mut rows = self.rows.clone();
+        let mut alive = vec![true; rows.len()];
+        let mut provenance = self
+            .row_labels
+            .iter()
+            .map(|&label| vec![label])
+            .collect_vec();
+
+        let mut column_rows: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for (row_index, row) in rows.iter().enumerate() {
+            for item in row.to_sorted_items() {
+                column_rows.entry(item).or_default().insert(row_index);
+            }
+        }
+
+        let mut column_weight: HashMap<usize, usize> = HashMap::new();
+        let mut weight_buckets: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for (&column, containing) in &column_rows {
+            column_weight.insert(column, containing.len());
+            weight_buckets
+                .entry(containing.len())
+                .or_default()
+                .insert(column);
+        }
+
+        fn reweight(
+            column_weight: &mut HashMap<usize, usize>,
+            weight_buckets: &mut HashMap<usize, HashSet<usize>>,
+            column: usize,
+            new_weight: usize,
+        ) {
+            if let Some(old_weight) = column_weight.insert(column, new_weight) {
+                if let Some(bucket) = weight_buckets.get_mut(&old_weight) {
+                    bucket.remove(&column);
+                    if bucket.is_empty() {
+                        weight_buckets.remove(&old_weight);
+                    }
+                }
+            }
+            weight_buckets.entry(new_weight).or_default().insert(column);
+        }
+
+        let mut alive_rows = rows.len();
+
+        loop {
+            let excess = column_weight.len().saturating_sub(alive_rows);
+            if excess <= min_excess {
+                break;
+            }
+
+            let Some(&column) = [0usize, 1, 2]
+                .iter()
+                .find_map(|weight| weight_buckets.get(weight))
+                .and_then(|bucket| bucket.iter().min())
+            else {
+                break;
+            };
+
+            match column_weight[&column] {
+                0 => {
+                    weight_buckets.get_mut(&0).unwrap().remove(&column);
+                    if weight_buckets[&0].is_empty() {
+                        weight_buckets.remove(&0);
+                    }
+                    column_weight.remove(&column);
+                    column_rows.remove(&column);
+                }
+
+                1 => {
+                    let row_index = *column_rows[&column].iter().next().unwrap();
+                    let items = rows[row_index].to_sorted_items();
+                    for item in items {
+                        let containing = column_rows.get_mut(&item).unwrap();
+                        containing.remove(&row_index);
+                        let new_weight = containing.len();
+                        reweight(&mut column_weight, &mut weight_buckets, item, new_weight);
+                    }
+                    alive[row_index] = false;
+                    alive_rows -= 1;
+                }
+
+                2 => {
+                    let mut containing = column_rows[&column].iter().copied();
+                    let keep = containing.next().unwrap();
+                    let donor = containing.next().unwrap();
+                    drop(containing);
+
+                    let keep_items: HashSet<usize> =
+                        rows[keep].to_sorted_items().into_iter().collect();
+                    let donor_items: HashSet<usize> =
+                        rows[donor].to_sorted_items().into_iter().collect();
+                    let donor_row = rows[donor].clone();
+
+                    rows[keep].add_assign_row(&donor_row, self.width);
+
+                    for &item in keep_items.intersection(&donor_items) {
+                        let set = column_rows.get_mut(&item).unwrap();
+                        set.remove(&keep);
+                        set.remove(&donor);
+                        let new_weight = set.len();
+                        reweight(&mut column_weight, &mut weight_buckets, item, new_weight);
+                    }
+                    for &item in donor_items.difference(&keep_items) {
+                        let set = column_rows.get_mut(&item).unwrap();
+                        set.remove(&donor);
+                        set.insert(keep);
+                    }
+
+                    let donor_provenance = std::mem::take(&mut provenance[donor]);
+                    provenance[keep].extend(donor_provenance);
+                    alive[donor] = false;
+                    alive_rows -= 1;
+                }
+
+                _ => unreachable!(),
+            }
+        }
+
+        let mut x_labels = column_weight.keys().copied().collect_vec();
+        x_labels.sort_unstable();
+        let width = column_width(&x_labels);
+
+        let mut new_rows = vec![];
+        let mut new_row_labels = vec![];
+        let mut new_provenance = vec![];
+
+        for row_index in 0..rows.len() {
+            if alive[row_index] {
+                new_rows.push(rows[row_index].clone());
+                new_row_labels.push(self.row_labels[row_index]);
+                new_provenance.push(std::mem::take(&mut provenance[row_index]));
+            }
+        }
+
+        (
+            CongruenceSystem {
+                rows: new_rows,
+                row_labels: new_row_labels,
+                x_labels,
+                width,
+            },
+            new_provenance,
+        )
+    }
+
     /// fast pivoting algorithm. Matrix is expected to have a column for each smooth number
     pub fn fast_pivot(&mut self) -> Vec<Vec<usize>> {
         assert!(self.x_labels.len() > self.rows.len());
         let mut marking = HashSet::new();
         let mut pivots = HashMap::new();
+        let width = self.width;
 
         for row_number in 0..self.rows.len() {
             let Some(i) = self.rows[row_number].least_term() else {
@@ -261,7 +800,7 @@ impl CongruenceSystem {
 
                 if self.rows[k].contains(i) {
                     let right_side = self.rows[row_number].clone();
-                    self.rows[k].add_inpace(&right_side);
+                    self.rows[k].add_assign_row(&right_side, width);
                 }
             }
         }
@@ -284,6 +823,371 @@ impl CongruenceSystem {
 
         result
     }
+
+    /// `B * v`: one output word per matrix row, each the XOR of the `v` columns that row touches.
+    fn apply_b(&self, v: &[u64]) -> Vec<u64> {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.to_sorted_items()
+                    .into_iter()
+                    .fold(0u64, |acc, column| acc ^ v[column])
+            })
+            .collect_vec()
+    }
+
+    /// `Bᵀ * u`: one output word per matrix column, scattered from the rows that touch it.
+    fn apply_b_transpose(&self, u: &[u64]) -> Vec<u64> {
+        let mut result = vec![0u64; self.width];
+        for (row, &value) in self.rows.iter().zip(u.iter()) {
+            for column in row.to_sorted_items() {
+                result[column] ^= value;
+            }
+        }
+        result
+    }
+
+    /// `A * v` where `A = Bᵀ·B`, computed as two sparse mat-vec passes without ever
+    /// materializing `A`.
+    fn apply_a(&self, v: &[u64]) -> Vec<u64> {
+        let bv = self.apply_b(v);
+        self.apply_b_transpose(&bv)
+    }
+
+    /// true if every row of `B` touches an even number of the given columns, i.e. `columns`
+    /// (as a 0/1 indicator vector) is genuinely in the null space of `B`.
+    fn is_null_vector(&self, columns: &HashSet<usize>) -> bool {
+        !columns.is_empty()
+            && self.rows.iter().all(|row| {
+                row.to_sorted_items()
+                    .iter()
+                    .filter(|column| columns.contains(column))
+                    .count()
+                    % 2
+                    == 0
+            })
+    }
+
+    /// Block Lanczos null-space solver over GF(2), for systems too large for [`Self::diagonalize`]'s
+    /// dense O(n³) elimination. Treats the system as sparse `B` (rows × columns) and finds vectors
+    /// `x` with `B·x = 0` by running the Krylov recurrence against `A = Bᵀ·B`, which is never
+    /// materialized - each step is two sparse mat-vec passes through `B` and `Bᵀ` instead.
+    ///
+    /// 64 candidate vectors are carried at once, packed one bit per `u64` ("block vectors"). Each
+    /// step computes `W = A·Vᵢ`, the small `64×64` Gram matrices `Vᵢᵀ·W` and `Wᵀ·W`, inverts the
+    /// largest invertible sub-block of `Vᵢᵀ·W` (tracking which of the 64 coordinates stay live via
+    /// a selection mask), and advances the three-term recurrence
+    /// `Vᵢ₊₁ = W·Sᵢ + Vᵢ·Cᵢ₊₁ + Vᵢ₋₁·Dᵢ₊₁`. The run stops once `Vᵢᵀ·A·Vᵢ` goes to zero, which
+    /// happens after roughly `columns / 64` steps.
+    ///
+    /// Every block produced along the way is checked column-by-column against `B` directly, so a
+    /// returned dependency is always a genuine null-space vector regardless of how exactly the
+    /// Krylov subspace happened to collapse. If the run doesn't turn up any (small systems, or an
+    /// unlucky starting block), the whole system is handed to the existing dense
+    /// [`Self::fast_pivot`] pass instead of iterating further; no reduced leftover system is formed.
+    ///
+    /// Returns each recovered dependency as a set of original `x_labels`, matching
+    /// [`Self::fast_pivot`]'s output contract.
+    pub fn block_lanczos(&self) -> Vec<Vec<usize>> {
+        if self.rows.is_empty() || self.width == 0 {
+            return vec![];
+        }
+
+        let mut rng = ChaCha8Rng::seed_from_u64(thread_rng().gen());
+
+        let mut v_prev = vec![0u64; self.width];
+        let mut v_curr = random_block_vector(self.width, &mut rng);
+
+        let max_iters = self.width / LANCZOS_BLOCK + 8;
+
+        let mut blocks = vec![];
+
+        for _ in 0..max_iters {
+            let w = self.apply_a(&v_curr);
+            let vt_w = block_gram(&v_curr, &w);
+
+            blocks.push(v_curr.clone());
+
+            if block_is_zero(&vt_w) {
+                break;
+            }
+
+            let (vt_w_inv, mask) = block_invert_with_mask(&vt_w);
+            let wt_w = block_gram(&w, &w);
+            let v_prev_t_w = block_gram(&v_prev, &w);
+
+            let w_s = block_vector_apply_mask(&w, mask);
+            let v_c = block_vector_mul(&v_curr, &block_mul(&vt_w_inv, &wt_w));
+            let v_d = block_vector_mul(&v_prev, &block_mul(&vt_w_inv, &v_prev_t_w));
+
+            let v_next = block_vector_xor(&block_vector_xor(&w_s, &v_c), &v_d);
+
+            v_prev = v_curr;
+            v_curr = v_next;
+        }
+
+        let mut dependencies = vec![];
+        let mut seen = HashSet::new();
+        let x_labels: HashSet<usize> = self.x_labels.iter().copied().collect();
+
+        for block in &blocks {
+            for bit in 0..LANCZOS_BLOCK {
+                let columns: HashSet<usize> = block
+                    .iter()
+                    .enumerate()
+                    .filter(|&(column, &word)| x_labels.contains(&column) && (word >> bit) & 1 == 1)
+                    .map(|(column, _)| column)
+                    .collect();
+
+                if !self.is_null_vector(&columns) {
+                    continue;
+                }
+
+                let mut labels = columns.into_iter().collect_vec();
+                labels.sort_unstable();
+
+                if seen.insert(labels.clone()) {
+                    dependencies.push(labels);
+                }
+            }
+        }
+
+        if dependencies.is_empty() {
+            let mut fallback = self.clone();
+            return fallback.fast_pivot();
+        }
+
+        dependencies
+    }
+
+    /// Dumps the system in (a small extension of) Matrix Market coordinate format: the standard
+    /// banner and `rows cols nnz` dimension line, one `row col 1` triple per set bit, plus two
+    /// `%row_labels`/`%x_labels` comment lines recording the original labels in order. The triples
+    /// use the labels themselves as row/column indices, not 1-based positions, so they can be 0 or
+    /// exceed the counts on the dimension line. [`Self::from_matrix_market`] uses the extra
+    /// comments to recover `row_labels`/`x_labels` exactly, including labels with no entries at
+    /// all, which the coordinate triples alone can't express.
+    pub fn to_matrix_market<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "%%MatrixMarket matrix coordinate integer general")?;
+        writeln!(writer, "%row_labels {}", self.row_labels.iter().join(" "))?;
+        writeln!(writer, "%x_labels {}", self.x_labels.iter().join(" "))?;
+
+        let entries = self
+            .row_labels
+            .iter()
+            .zip(self.rows.iter())
+            .flat_map(|(&row_label, row)| {
+                row.to_sorted_items()
+                    .into_iter()
+                    .map(move |column| (row_label, column))
+            })
+            .collect_vec();
+
+        writeln!(
+            writer,
+            "{} {} {}",
+            self.row_labels.len(),
+            self.x_labels.len(),
+            entries.len()
+        )?;
+
+        for (row_label, column) in entries {
+            writeln!(writer, "{row_label} {column} 1")?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads back a system written by [`Self::to_matrix_market`]. Requires the `%row_labels` and
+    /// `%x_labels` comment lines this crate writes (a matrix dumped by some other Matrix Market
+    /// writer, without those, is rejected rather than guessed at).
+    pub fn from_matrix_market<R: BufRead>(reader: R) -> Result<CongruenceSystem, String> {
+        let parse_usize_list = |text: &str| -> Result<Vec<usize>, String> {
+            text.split_whitespace()
+                .map(|token| {
+                    token
+                        .parse::<usize>()
+                        .map_err(|e| format!("invalid integer '{token}': {e}"))
+                })
+                .collect()
+        };
+
+        let mut row_labels = None;
+        let mut x_labels = None;
+        let mut dimensions_seen = false;
+        let mut entries = vec![];
+
+        for line in reader.lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix("%row_labels") {
+                row_labels = Some(parse_usize_list(rest)?);
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix("%x_labels") {
+                x_labels = Some(parse_usize_list(rest)?);
+                continue;
+            }
+
+            if line.starts_with('%') {
+                continue;
+            }
+
+            if !dimensions_seen {
+                dimensions_seen = true;
+                continue;
+            }
+
+            let triple = parse_usize_list(line)?;
+            let [row_label, column, _value] = triple[..] else {
+                return Err(format!("expected 'row col value' triple, got '{line}'"));
+            };
+            entries.push((row_label, column));
+        }
+
+        let row_labels = row_labels.ok_or("missing %row_labels comment line")?;
+        let x_labels = x_labels.ok_or("missing %x_labels comment line")?;
+
+        let position_of_row: HashMap<usize, usize> = row_labels
+            .iter()
+            .enumerate()
+            .map(|(position, &label)| (label, position))
+            .collect();
+
+        let mut rows: SparseCountMap = vec![vec![]; row_labels.len()];
+        for (row_label, column) in entries {
+            let &position = position_of_row
+                .get(&row_label)
+                .ok_or_else(|| format!("row label {row_label} not listed in %row_labels"))?;
+            rows[position].push((column, 1));
+        }
+
+        Ok(CongruenceSystem::with_labels(&rows, x_labels, row_labels))
+    }
+}
+
+/// a disjoint-set over a fixed universe of `usize` ids, used by [`combine_large_primes`] to
+/// track which partial relations have already been linked into a large-prime chain.
+pub struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<usize>,
+}
+
+impl UnionFind {
+    pub fn new(size: usize) -> Self {
+        UnionFind {
+            parent: (0..size).collect_vec(),
+            rank: vec![0; size],
+        }
+    }
+
+    pub fn find(&mut self, item: usize) -> usize {
+        if self.parent[item] != item {
+            self.parent[item] = self.find(self.parent[item]);
+        }
+        self.parent[item]
+    }
+
+    /// links the two sets together, returning `true` if they were previously distinct (a new
+    /// tree edge) or `false` if `first` and `second` were already in the same set (a cycle).
+    pub fn union(&mut self, first: usize, second: usize) -> bool {
+        let (first_root, second_root) = (self.find(first), self.find(second));
+
+        if first_root == second_root {
+            return false;
+        }
+
+        match self.rank[first_root].cmp(&self.rank[second_root]) {
+            Ordering::Less => self.parent[first_root] = second_root,
+            Ordering::Greater => self.parent[second_root] = first_root,
+            Ordering::Equal => {
+                self.parent[second_root] = first_root;
+                self.rank[first_root] += 1;
+            }
+        }
+
+        true
+    }
+}
+
+/// a relation left over from sieving that may not be fully smooth: `large_prime` is set when
+/// one factor beyond the factor base remains, mirroring [`crate::sieve::SmoothNumber`] but at
+/// the symbolic, exponent-count level the solver works with.
+#[derive(Clone, Debug)]
+pub struct PartialRelation {
+    pub divisors: Vec<(usize, usize)>,
+    pub large_prime: Option<usize>,
+}
+
+/// merges two divisor/exponent lists into one, summing exponents where the same prime shows up
+/// in both (mirrors [`crate::sieve::combine_two_partials`]'s bookkeeping, just without the
+/// numeric side of multiplying the actual relations together).
+fn merge_divisor_counts(
+    first: &[(usize, usize)],
+    second: &[(usize, usize)],
+) -> Vec<(usize, usize)> {
+    let mut merged = first.to_vec();
+
+    for &(prime, exponent) in second {
+        match merged.iter_mut().find(|(p, _)| *p == prime) {
+            Some((_, existing)) => *existing += exponent,
+            None => merged.push((prime, exponent)),
+        }
+    }
+
+    merged
+}
+
+/// preprocessing stage that glues partial relations sharing a large prime into full ones, modeled
+/// on the classic union-find + checklist technique: relations are scanned in order, and a
+/// `checklist` remembers, per large prime, the most recent partial still waiting for a partner.
+/// The first partial carrying a given large prime is simply recorded; the next one sharing it is
+/// paired with it and the large prime cancels mod 2 when the two rows are merged.
+///
+/// A large prime seen more than twice keeps being paired against the newest unresolved partial,
+/// which produces a spanning tree over all the partials sharing it (a simple chain) rather than
+/// leaving the extra ones unpaired. `UnionFind` tracks which partials are already connected, so
+/// if a large prime were ever rejoined across an already-linked pair - a cycle - the pairing is
+/// still emitted as an extra, independent combined relation instead of being skipped.
+///
+/// Relations with `large_prime: None` are already full and pass through unchanged. Returns the
+/// synthesized [`SparseCountMap`] (ready for [`CongruenceSystem::new`]) together with, for each
+/// emitted row, the original `partials` indices that were combined to produce it.
+pub fn combine_large_primes(partials: &[PartialRelation]) -> (SparseCountMap, Vec<Vec<usize>>) {
+    let mut rows = vec![];
+    let mut provenance = vec![];
+
+    let mut checklist: HashMap<usize, usize> = HashMap::new();
+    let mut union_find = UnionFind::new(partials.len());
+
+    for (index, partial) in partials.iter().enumerate() {
+        match partial.large_prime {
+            None => {
+                rows.push(partial.divisors.clone());
+                provenance.push(vec![index]);
+            }
+            Some(large_prime) => {
+                if let Some(&pending) = checklist.get(&large_prime) {
+                    union_find.union(pending, index);
+                    rows.push(merge_divisor_counts(
+                        &partials[pending].divisors,
+                        &partial.divisors,
+                    ));
+                    provenance.push(vec![pending, index]);
+                }
+
+                checklist.insert(large_prime, index);
+            }
+        }
+    }
+
+    (rows, provenance)
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -345,15 +1249,18 @@ pub fn produce_solution(system: &CongruenceSystem) -> Solution {
             break;
         };
 
-        if row.items.len() == 1 {
+        if row.weight() == 1 {
             constants.insert(term);
             const_row_indices.insert(i);
         }
     }
 
     let dependencies = system.rows.iter().rev().filter(|row| {
-        row.items.len() >= 2 && {
-            let items = row.items.iter().cloned().collect::<HashSet<usize>>();
+        row.weight() >= 2 && {
+            let items = row
+                .to_sorted_items()
+                .into_iter()
+                .collect::<HashSet<usize>>();
             let deps = items.difference(&constants);
             deps.count() > 1
         }
@@ -366,9 +1273,8 @@ pub fn produce_solution(system: &CongruenceSystem) -> Solution {
     for dep in dependencies {
         dependant_vars.insert(dep.least_term().unwrap());
         let right_side = dep
-            .items
-            .iter()
-            .cloned()
+            .to_sorted_items()
+            .into_iter()
             .skip(1)
             .filter(|item| !constants.contains(item))
             .collect::<HashSet<_>>();
@@ -411,7 +1317,11 @@ pub fn produce_solution(system: &CongruenceSystem) -> Solution {
 #[cfg(test)]
 mod tests {
 
-    use super::{produce_solution, CongruenceSystem, Dependency, Solution, SparseRow};
+    use super::{
+        block_gram, block_identity, block_invert_with_mask, block_mul, combine_large_primes,
+        produce_solution, CongruenceSystem, Dependency, MatrixRow, PackedRow, PartialRelation,
+        RowRepr, Solution, SparseRow, UnionFind,
+    };
     use std::collections::HashSet;
 
     macro_rules! set {
@@ -517,4 +1427,256 @@ mod tests {
             vec![0, 1, 4].unorder()
         )
     }
+
+    #[test]
+    fn packed_row_matches_sparse_xor() {
+        let sparse_a = SparseRow { items: vec![1, 5] };
+        let sparse_b = SparseRow {
+            items: vec![2, 5, 6],
+        };
+
+        let mut expected = sparse_a.clone();
+        expected.add_inpace(&sparse_b);
+
+        let mut packed_a = PackedRow::from_items(&sparse_a.items, 8);
+        let packed_b = PackedRow::from_items(&sparse_b.items, 8);
+        packed_a.add_assign_row(&packed_b);
+
+        assert_eq!(packed_a.to_sorted_items(), expected.items);
+    }
+
+    #[test]
+    fn matrix_row_promotes_past_density_threshold() {
+        let width = 8;
+        let mut row = MatrixRow::Sparse(SparseRow {
+            items: vec![0, 1, 2],
+        });
+        let addend = MatrixRow::Sparse(SparseRow { items: vec![3] });
+
+        row.add_assign_row(&addend, width);
+
+        assert!(matches!(row, MatrixRow::Packed(_)));
+        assert_eq!(row.to_sorted_items(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn matrix_row_stays_sparse_when_not_dense() {
+        let width = 1000;
+        let mut row = MatrixRow::Sparse(SparseRow { items: vec![0] });
+        let addend = MatrixRow::Sparse(SparseRow { items: vec![1] });
+
+        row.add_assign_row(&addend, width);
+
+        assert!(matches!(row, MatrixRow::Sparse(_)));
+        assert_eq!(row.to_sorted_items(), vec![0, 1]);
+    }
+
+    #[test]
+    fn filter_drops_row_whose_columns_are_unique() {
+        // row 300 is the only relation touching columns 3 and 4, so it can never
+        // contribute to a null-space combination and must be dropped outright.
+        let system = CongruenceSystem::with_labels(
+            &vec![
+                vec![(1, 1), (2, 1)],
+                vec![(1, 1), (2, 1)],
+                vec![(3, 1), (4, 1)],
+            ],
+            vec![1, 2, 3, 4],
+            vec![100, 200, 300],
+        );
+
+        let (reduced, provenance) = system.filter(0);
+
+        assert_eq!(reduced.row_labels, vec![100, 200]);
+        assert_eq!(reduced.x_labels, vec![1, 2]);
+        assert_eq!(provenance, vec![vec![100], vec![200]]);
+        assert!(reduced.rows[0].contains(1) && reduced.rows[0].contains(2));
+        assert!(reduced.rows[1].contains(1) && reduced.rows[1].contains(2));
+    }
+
+    #[test]
+    fn filter_merges_identical_relations_and_tracks_provenance() {
+        // rows 100 and 200 are identical relations, so XORing one into the other
+        // proves they cancel out completely and only one survives.
+        let system = CongruenceSystem::with_labels(
+            &vec![vec![(1, 1), (2, 1), (3, 1)], vec![(1, 1), (2, 1), (3, 1)]],
+            vec![1, 2, 3],
+            vec![100, 200],
+        );
+
+        let (reduced, provenance) = system.filter(0);
+
+        assert_eq!(reduced.row_labels.len(), 1);
+        assert_eq!(provenance.len(), 1);
+        assert_eq!(provenance[0].clone().unorder(), vec![100, 200].unorder());
+        assert!(reduced.rows[0].is_zero());
+    }
+
+    #[test]
+    fn union_find_joins_sets_and_detects_cycles() {
+        let mut uf = UnionFind::new(4);
+        assert!(uf.union(0, 1));
+        assert!(uf.union(2, 3));
+        assert_ne!(uf.find(0), uf.find(2));
+        assert!(uf.union(1, 2));
+        assert_eq!(uf.find(0), uf.find(3));
+        assert!(!uf.union(0, 3));
+    }
+
+    #[test]
+    fn combine_large_primes_pairs_exactly_two_partials() {
+        let partials = vec![
+            PartialRelation {
+                divisors: vec![(2, 1), (3, 1)],
+                large_prime: Some(101),
+            },
+            PartialRelation {
+                divisors: vec![(3, 1), (5, 1)],
+                large_prime: Some(101),
+            },
+        ];
+
+        let (rows, provenance) = combine_large_primes(&partials);
+        let mut combined = rows[0].clone();
+        combined.sort_unstable();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(combined, vec![(2, 1), (3, 2), (5, 1)]);
+        assert_eq!(provenance, vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn combine_large_primes_chains_more_than_two_partials() {
+        // three partials sharing the same large prime form a spanning tree of two
+        // edges (0-1, 1-2) instead of leaving the third one unpaired.
+        let partials = vec![
+            PartialRelation {
+                divisors: vec![(2, 1)],
+                large_prime: Some(7),
+            },
+            PartialRelation {
+                divisors: vec![(3, 1)],
+                large_prime: Some(7),
+            },
+            PartialRelation {
+                divisors: vec![(5, 1)],
+                large_prime: Some(7),
+            },
+        ];
+
+        let (rows, provenance) = combine_large_primes(&partials);
+        let mut first = rows[0].clone();
+        let mut second = rows[1].clone();
+        first.sort_unstable();
+        second.sort_unstable();
+
+        assert_eq!(provenance, vec![vec![0, 1], vec![1, 2]]);
+        assert_eq!(first, vec![(2, 1), (3, 1)]);
+        assert_eq!(second, vec![(3, 1), (5, 1)]);
+    }
+
+    #[test]
+    fn combine_large_primes_passes_through_full_relations() {
+        let partials = vec![PartialRelation {
+            divisors: vec![(2, 1), (3, 1)],
+            large_prime: None,
+        }];
+
+        let (rows, provenance) = combine_large_primes(&partials);
+
+        assert_eq!(rows, vec![vec![(2, 1), (3, 1)]]);
+        assert_eq!(provenance, vec![vec![0]]);
+    }
+
+    #[test]
+    fn block_mul_identity_is_noop() {
+        let identity = block_identity();
+        let mut matrix = [0u64; 64];
+        matrix[0] = 0b101;
+        matrix[1] = 0b1;
+
+        assert_eq!(block_mul(&identity, &matrix), matrix);
+        assert_eq!(block_mul(&matrix, &identity), matrix);
+    }
+
+    #[test]
+    fn block_gram_matches_hand_computed_values() {
+        // u has a single live column (index 0) with bits {0, 2} set; v's only column has bit 1
+        // set. u^T v should therefore have two nonzero rows (row 0 and row 2), each equal
+        // to v's column.
+        let u = vec![0b101u64];
+        let v = vec![0b10u64];
+
+        let gram = block_gram(&u, &v);
+
+        assert_eq!(gram[0], 0b10);
+        assert_eq!(gram[1], 0);
+        assert_eq!(gram[2], 0b10);
+    }
+
+    #[test]
+    fn block_invert_with_mask_recovers_inverse_of_full_rank_block() {
+        let identity = block_identity();
+        let (inverse, mask) = block_invert_with_mask(&identity);
+
+        assert_eq!(mask, u64::MAX);
+        assert_eq!(inverse, identity);
+    }
+
+    #[test]
+    fn block_invert_with_mask_skips_singular_columns() {
+        let mut singular = block_identity();
+        singular[1] = 0; // row 1 is entirely zero, so column 1 can never find a pivot
+
+        let (_, mask) = block_invert_with_mask(&singular);
+
+        assert_eq!(mask & (1 << 1), 0);
+        assert_eq!(mask.count_ones(), 63);
+    }
+
+    #[test]
+    fn block_lanczos_returns_genuine_null_space_vectors() {
+        // rows 0 and 1 touch columns {0,1} and {0,2} respectively; the system has more columns
+        // than rows, so some nonempty subset of columns must XOR every row to zero.
+        let system = CongruenceSystem::with_labels(
+            &vec![vec![(0, 1), (1, 1)], vec![(0, 1), (2, 1)]],
+            vec![0usize, 1, 2],
+            vec![0usize, 1],
+        );
+
+        let dependencies = system.block_lanczos();
+
+        assert!(!dependencies.is_empty());
+        for dependency in &dependencies {
+            let columns: HashSet<usize> = dependency.iter().cloned().collect();
+            assert!(system.is_null_vector(&columns));
+        }
+    }
+
+    #[test]
+    fn matrix_market_round_trips_labels_and_solution() {
+        let system = CongruenceSystem::with_labels(
+            &vec![
+                vec![(0, 1), (1, 1)],
+                vec![(1, 1), (2, 1)],
+                vec![], // an all-zero row, which leaves no coordinate triples at all
+            ],
+            vec![0usize, 1, 2],
+            vec![100usize, 200, 300],
+        );
+
+        let mut buffer = vec![];
+        system.to_matrix_market(&mut buffer).unwrap();
+
+        let reloaded = CongruenceSystem::from_matrix_market(buffer.as_slice()).unwrap();
+
+        assert!(reloaded == system);
+        assert!(produce_solution(&reloaded) == produce_solution(&system));
+    }
+
+    #[test]
+    fn matrix_market_rejects_missing_label_comments() {
+        let text = "%%MatrixMarket matrix coordinate integer general\n1 1 1\n0 0 1\n";
+        assert!(CongruenceSystem::from_matrix_market(text.as_bytes()).is_err());
+    }
 }